@@ -0,0 +1,51 @@
+#![allow(clippy::single_match)]
+
+#[cfg(all(windows_platform, feature = "tray-menu"))]
+fn main() -> Result<(), impl std::error::Error> {
+    use simple_logger::SimpleLogger;
+    use winit::{
+        event::Event,
+        event_loop::EventLoop,
+        tray::{StatusTray, StatusTrayEvent, TrayEvent, TrayMenu},
+        window::Icon,
+    };
+
+    SimpleLogger::new().init().unwrap();
+    let event_loop = EventLoop::new().unwrap();
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/examples/icon.png");
+    let image = image::open(path)
+        .expect("Failed to open icon path")
+        .into_rgba8();
+    let (width, height) = image.dimensions();
+    let icon = Icon::from_rgba(image.into_raw(), width, height).expect("Failed to open icon");
+
+    let mut menu = TrayMenu::new();
+    let hello = menu.item("Say &hello");
+    let (_tray, events) = StatusTray::new(icon)
+        .tooltip("Status tray example")
+        .menu(menu)
+        .on_activate(|| println!("Activated"))
+        .build(&event_loop)
+        .unwrap();
+
+    event_loop.run(move |event, elwt| match event {
+        Event::AboutToWait => {
+            while let Ok(event) = events.try_recv() {
+                match event {
+                    StatusTrayEvent::Quit => elwt.exit(),
+                    StatusTrayEvent::Tray(TrayEvent::MenuItemClicked { id, .. }) if id == hello => {
+                        println!("Hello!")
+                    }
+                    StatusTrayEvent::Tray(event) => println!("{event:?}"),
+                }
+            }
+        }
+        _ => (),
+    })
+}
+
+#[cfg(not(all(windows_platform, feature = "tray-menu")))]
+fn main() {
+    println!("This example is only supported on Windows, with the `tray-menu` feature");
+}
@@ -49,6 +49,7 @@ use crate::{
     event_loop::AsyncRequestSerial,
     keyboard::{self, ModifiersKeyState, ModifiersKeys, ModifiersState},
     platform_impl,
+    tray::{TrayEvent, TrayId},
     window::{ActivationToken, Theme, WindowId},
 };
 
@@ -71,6 +72,12 @@ pub enum Event<T: 'static> {
         event: WindowEvent,
     },
 
+    /// Emitted when the OS sends an event to a winit tray icon.
+    TrayEvent {
+        tray_id: TrayId,
+        event: TrayEvent,
+    },
+
     /// Emitted when the OS sends an event to a device.
     DeviceEvent {
         device_id: DeviceId,
@@ -275,6 +282,7 @@ impl<T> Event<T> {
         match self {
             UserEvent(_) => Err(self),
             WindowEvent { window_id, event } => Ok(WindowEvent { window_id, event }),
+            TrayEvent { tray_id, event } => Ok(TrayEvent { tray_id, event }),
             DeviceEvent { device_id, event } => Ok(DeviceEvent { device_id, event }),
             NewEvents(cause) => Ok(NewEvents(cause)),
             AboutToWait => Ok(AboutToWait),
@@ -1247,6 +1255,39 @@ mod tests {
                 with_window_event(Occluded(true));
             }
 
+            {
+                use crate::tray::{TrayEvent::*, TrayId};
+
+                let with_tray_event = |tev| {
+                    x(event::Event::TrayEvent {
+                        tray_id: TrayId(0),
+                        event: tev,
+                    })
+                };
+
+                with_tray_event(LeftClicked {
+                    position: (0, 0).into(),
+                    from_overflow: false,
+                });
+                with_tray_event(RightClicked {
+                    position: (0, 0).into(),
+                    from_overflow: false,
+                });
+                #[cfg(feature = "tray-menu")]
+                with_tray_event(MenuItemClicked {
+                    id: crate::tray::MenuId(0),
+                    data: None,
+                });
+                with_tray_event(IconMoved(Default::default()));
+                with_tray_event(DisplayChanged(None));
+                with_tray_event(VisibilityChanged(crate::tray::IconVisibility::Hidden));
+                #[cfg(feature = "tray-notifications")]
+                with_tray_event(NotificationAction {
+                    notification: None,
+                    action_id: String::new(),
+                });
+            }
+
             #[allow(deprecated)]
             {
                 use event::DeviceEvent::*;
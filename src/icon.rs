@@ -28,6 +28,8 @@ pub enum BadIcon {
     },
     /// Produced when underlying OS functionality failed to create the icon
     OsError(io::Error),
+    /// Produced when [`Icon::from_png_bytes`] is given data that isn't a valid PNG image.
+    InvalidPng { reason: String },
 }
 
 impl fmt::Display for BadIcon {
@@ -45,6 +47,7 @@ impl fmt::Display for BadIcon {
                 "The specified dimensions ({width:?}x{height:?}) don't match the number of pixels supplied by the `rgba` argument ({pixel_count:?}). For those dimensions, the expected pixel count is {width_x_height:?}.",
             ),
             BadIcon::OsError(e) => write!(f, "OS error when instantiating the icon: {e:?}"),
+            BadIcon::InvalidPng { reason } => write!(f, "Failed to decode the PNG image: {reason}"),
         }
     }
 }
@@ -122,4 +125,39 @@ impl Icon {
             inner: PlatformIcon::from_rgba(rgba, width, height)?,
         })
     }
+
+    /// Creates an icon from the content of a PNG file.
+    ///
+    /// This is convenient with [`include_bytes!`]. Returns a [`BadIcon::InvalidPng`] error if
+    /// `bytes` can't be decoded.
+    #[cfg(feature = "png")]
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, BadIcon> {
+        let invalid_png = |err: png::DecodingError| BadIcon::InvalidPng {
+            reason: err.to_string(),
+        };
+
+        let mut decoder = png::Decoder::new(bytes);
+        // Expands palettes, low bit depths and transparency chunks, and strips 16 bit channels.
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(invalid_png)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(invalid_png)?;
+        buffer.truncate(frame.buffer_size());
+
+        let rgba = match frame.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks_exact(2)
+                .flat_map(|la| [la[0], la[0], la[0], la[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&l| [l, l, l, u8::MAX]).collect(),
+            png::ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
+        };
+
+        Self::from_rgba(rgba, frame.width, frame.height)
+    }
 }
@@ -3,11 +3,12 @@ use std::{ffi::c_void, path::Path};
 use crate::{
     dpi::PhysicalSize,
     event::{DeviceId, KeyEvent},
-    event_loop::EventLoopBuilder,
+    event_loop::{EventLoopBuilder, EventLoopWindowTarget},
     keyboard::Key,
     monitor::MonitorHandle,
     platform::modifier_supplement::KeyEventExtModifierSupplement,
     platform_impl::WinIcon,
+    tray::{Tray, TrayBuilder, TrayError},
     window::{BadIcon, Icon, Window, WindowBuilder},
 };
 
@@ -265,6 +266,187 @@ impl WindowBuilderExtWindows for WindowBuilder {
     }
 }
 
+/// Additional methods on `TrayBuilder` that are specific to Windows.
+pub trait TrayBuilderExtWindows {
+    /// Sets the AppUserModelID notifications of the tray are shown with, which makes them toasts
+    /// instead of balloons.
+    ///
+    /// The ID must be registered with the shell, usually through a Start menu shortcut carrying
+    /// it, for the toasts to be displayed.
+    #[cfg(feature = "tray-toast")]
+    fn with_app_user_model_id<S: Into<String>>(self, app_user_model_id: S) -> Self;
+
+    /// Loads the icon of the tray from the `.ico` file at `path`, replacing the icon given with
+    /// [`TrayBuilder::with_icon`].
+    ///
+    /// Of the sizes the file contains, the one closest to the size of tray icons is used, and
+    /// another one is picked when the DPI changes, so the icon stays crisp. Setting another
+    /// icon on the tray stops this.
+    fn with_icon_from_path<P: AsRef<Path>>(self, path: P) -> Self;
+
+    /// Sets the message the shell sends to the window of the tray to notify it of clicks and
+    /// other interactions with the icon, for applications defining their own messages for that
+    /// window. The default is `WM_USER + 1`.
+    ///
+    /// The message must be between `WM_USER` and `0xBFFF`, or [`TrayBuilder::build`] fails with
+    /// [`TrayError::InvalidCallbackMessage`](crate::tray::TrayError::InvalidCallbackMessage).
+    fn with_callback_message(self, message: u32) -> Self;
+
+    /// Uses the existing popup menu `hmenu` as the menu of the tray, replacing the menu given
+    /// with [`TrayBuilder::with_menu`], for menus built with other tooling.
+    ///
+    /// Clicking an item emits a
+    /// [`TrayEvent::MenuItemClicked`](crate::tray::TrayEvent::MenuItemClicked) with the
+    /// identifier of the item, and the menu is switched to report its items through
+    /// `WM_MENUCOMMAND` for that.
+    ///
+    /// The identifiers of the items must be below `0x10000`, as higher ones are reserved for the
+    /// items of [`TrayMenu`](crate::tray::TrayMenu)s, or [`TrayBuilder::build`] fails with
+    /// [`TrayError::InvalidMenuItemId`](crate::tray::TrayError::InvalidMenuItemId).
+    ///
+    /// If `owned` is set, the menu is destroyed along with the tray. Otherwise it must outlive
+    /// the tray, and the application destroys it afterwards.
+    #[cfg(feature = "tray-menu")]
+    fn with_raw_menu(self, hmenu: HMENU, owned: bool) -> Self;
+}
+
+impl TrayBuilderExtWindows for TrayBuilder {
+    #[inline]
+    #[cfg(feature = "tray-toast")]
+    fn with_app_user_model_id<S: Into<String>>(mut self, app_user_model_id: S) -> Self {
+        self.platform_specific.app_user_model_id = Some(app_user_model_id.into());
+        self
+    }
+
+    #[inline]
+    fn with_icon_from_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.platform_specific.icon_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    #[inline]
+    fn with_callback_message(mut self, message: u32) -> Self {
+        self.platform_specific.callback_message = Some(message);
+        self
+    }
+
+    #[inline]
+    #[cfg(feature = "tray-menu")]
+    fn with_raw_menu(mut self, hmenu: HMENU, owned: bool) -> Self {
+        self.platform_specific.raw_menu = Some((hmenu, owned));
+        self
+    }
+}
+
+/// Identifies a tray icon to the shell, like `NOTIFYICONIDENTIFIER`, see
+/// [`TrayExtWindows::icon_identifier`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NotifyIconIdentifier {
+    /// The window receiving the notifications of the icon.
+    pub hwnd: HWND,
+    /// The identifier of the icon among those of `hwnd`.
+    pub uid: u32,
+    /// The GUID identifying the icon instead of `hwnd` and `uid`, as returned by
+    /// `GUID::to_u128`. `None` unless the icon was added with a GUID.
+    pub guid: Option<u128>,
+}
+
+/// A stock icon of the shell, see [`TrayExtWindows::set_shell_icon`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShellIcon {
+    Info,
+    Warning,
+    Error,
+    Help,
+    /// The shield shown on actions requiring elevation.
+    Shield,
+    Lock,
+    Key,
+    Delete,
+    Find,
+    World,
+    Users,
+    Printer,
+}
+
+/// Additional methods on `Tray` that are specific to Windows.
+pub trait TrayExtWindows {
+    /// Returns the identity the icon was added to the shell with, for use with shell APIs such
+    /// as `Shell_NotifyIconGetRect`.
+    ///
+    /// The icon is removed along with the tray, after which the identifier is stale.
+    fn icon_identifier(&self) -> NotifyIconIdentifier;
+
+    /// Sets the icon of the tray to a stock icon of the shell, for generic statuses that don't
+    /// warrant shipping icons. Like [`Tray::set_icon`], this can be called from any thread.
+    ///
+    /// The icon is loaded at the size of small icons for the system DPI. Fails with
+    /// [`TrayError::InvalidIcon`] if the running version of Windows doesn't provide it.
+    fn set_shell_icon(&self, icon: ShellIcon) -> Result<(), TrayError>;
+
+    /// Takes over an icon added with `Shell_NotifyIconW` by other code, to migrate such code
+    /// to [`Tray`] incrementally.
+    ///
+    /// The window of the icon is subclassed, and the icon is modified to notify it with a
+    /// message registered by winit, which is turned into [`TrayEvent`]s like for other trays.
+    /// The tray owns the icon from then on: dropping it removes the icon and the subclass, but
+    /// leaves the window alone. If the window is destroyed first, the icon is removed along
+    /// with it.
+    ///
+    /// The tray starts without a menu and doesn't know the current icon, so
+    /// [`Tray::set_icon_tint`] applies to the generic application icon until another icon is
+    /// set.
+    ///
+    /// The tray gets a [`TrayId`] of its own, so the `uid` of the icon may be anything, even
+    /// the `uID` of another tray of another window.
+    ///
+    /// Fails with [`TrayError::WrongThread`] off the thread running `event_loop`, and if
+    /// `identifier` has a GUID, which isn't supported, or designates an icon that already is a
+    /// tray.
+    ///
+    /// # Safety
+    ///
+    /// `identifier.hwnd` must be a valid window created on the thread running `event_loop`,
+    /// with an icon `identifier.uid`. The other code must not modify or remove the icon, nor
+    /// subclass the window with the same subclass identifier, afterwards.
+    ///
+    /// [`TrayEvent`]: crate::tray::TrayEvent
+    /// [`TrayId`]: crate::tray::TrayId
+    unsafe fn from_raw<T: 'static>(
+        event_loop: &EventLoopWindowTarget<T>,
+        identifier: NotifyIconIdentifier,
+    ) -> Result<Self, TrayError>
+    where
+        Self: Sized;
+}
+
+impl TrayExtWindows for Tray {
+    #[inline]
+    fn icon_identifier(&self) -> NotifyIconIdentifier {
+        let (hwnd, uid) = self.0.icon_identifier();
+        NotifyIconIdentifier {
+            hwnd,
+            uid,
+            guid: None,
+        }
+    }
+
+    #[inline]
+    fn set_shell_icon(&self, icon: ShellIcon) -> Result<(), TrayError> {
+        self.0.set_shell_icon(icon)
+    }
+
+    #[inline]
+    unsafe fn from_raw<T: 'static>(
+        event_loop: &EventLoopWindowTarget<T>,
+        identifier: NotifyIconIdentifier,
+    ) -> Result<Self, TrayError> {
+        let NotifyIconIdentifier { hwnd, uid, guid } = identifier;
+        unsafe { crate::platform_impl::Tray::from_raw(&event_loop.p, hwnd, uid, guid) }.map(Tray)
+    }
+}
+
 /// Additional methods on `MonitorHandle` that are specific to Windows.
 pub trait MonitorHandleExtWindows {
     /// Returns the name of the monitor adapter specific to the Win32 API.
@@ -42,6 +42,10 @@ pub(crate) struct EventLoopRunner<T: 'static> {
     event_handler: EventHandler<T>,
     event_buffer: RefCell<VecDeque<BufferedEvent<T>>>,
 
+    // Whether `run_on_demand` or `pump_events` is running, and so processing the messages posted
+    // to the windows of the thread. The event handler can't tell, as it's taken while called.
+    pumping: Cell<bool>,
+
     panic_error: Cell<Option<PanicError>>,
 }
 
@@ -78,6 +82,7 @@ impl<T> EventLoopRunner<T> {
             last_events_cleared: Cell::new(Instant::now()),
             event_handler: Cell::new(None),
             event_buffer: RefCell::new(VecDeque::new()),
+            pumping: Cell::new(false),
         }
     }
 
@@ -103,10 +108,12 @@ impl<T> EventLoopRunner<T> {
         };
         let old_event_handler = self.event_handler.replace(Some(f));
         assert!(old_event_handler.is_none());
+        self.pumping.set(true);
     }
 
     pub(crate) fn clear_event_handler(&self) {
         self.event_handler.set(None);
+        self.pumping.set(false);
     }
 
     pub(crate) fn reset_runner(&self) {
@@ -120,12 +127,14 @@ impl<T> EventLoopRunner<T> {
             last_events_cleared: _,
             event_handler,
             event_buffer: _,
+            pumping,
         } = self;
         interrupt_msg_dispatch.set(false);
         runner_state.set(RunnerState::Uninitialized);
         panic_error.set(None);
         exit.set(None);
         event_handler.set(None);
+        pumping.set(false);
     }
 }
 
@@ -143,6 +152,12 @@ impl<T> EventLoopRunner<T> {
         }
     }
 
+    /// Whether messages posted to the windows of the thread are currently processed, as opposed
+    /// to waiting for the next call to `run_on_demand` or `pump_events`.
+    pub fn is_pumping(&self) -> bool {
+        self.pumping.get()
+    }
+
     pub fn state(&self) -> RunnerState {
         self.runner_state.get()
     }
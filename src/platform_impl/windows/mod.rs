@@ -1,5 +1,7 @@
 #![cfg(windows_platform)]
 
+use std::path::PathBuf;
+
 use smol_str::SmolStr;
 use windows_sys::Win32::{
     Foundation::{HANDLE, HWND},
@@ -13,7 +15,10 @@ pub(crate) use self::{
     icon::WinIcon,
     monitor::{MonitorHandle, VideoMode},
     window::Window,
-    tray::Tray,
+    tray::{
+        tray_id_from_window, window_from_tray_id, Tray, TrayUpdater,
+        WINDOW_CLASS as TRAY_WINDOW_CLASS,
+    },
 };
 
 pub use self::icon::WinIcon as PlatformIcon;
@@ -53,6 +58,17 @@ impl Default for PlatformSpecificWindowBuilderAttributes {
 unsafe impl Send for PlatformSpecificWindowBuilderAttributes {}
 unsafe impl Sync for PlatformSpecificWindowBuilderAttributes {}
 
+#[derive(Clone, Default)]
+pub struct PlatformSpecificTrayBuilderAttributes {
+    pub icon_path: Option<PathBuf>,
+    pub callback_message: Option<u32>,
+    /// A menu given as is, and whether the tray destroys it.
+    #[cfg(feature = "tray-menu")]
+    pub raw_menu: Option<(HMENU, bool)>,
+    #[cfg(feature = "tray-toast")]
+    pub app_user_model_id: Option<String>,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct DeviceId(u32);
 
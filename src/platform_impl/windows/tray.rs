@@ -1,110 +1,621 @@
-use std::{cell::Cell, ops::Deref};
+use std::{
+    cell::{Cell, RefCell},
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    path::Path,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::Sender,
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use rwh_06::RawWindowHandle;
 use windows_sys::Win32::{
-    Foundation::{HWND, LPARAM, LRESULT, POINT, WPARAM},
-    System::LibraryLoader::GetModuleHandleW,
+    Foundation::{ERROR_TIMEOUT, HWND, LPARAM, LRESULT, POINT, RECT, S_OK, WPARAM},
+    Graphics::Gdi::{
+        GetMonitorInfoW, MonitorFromPoint, MonitorFromRect, HMONITOR, MONITORINFO,
+        MONITOR_DEFAULTTONEAREST, MONITOR_DEFAULTTOPRIMARY,
+    },
+    System::{LibraryLoader::GetModuleHandleW, Threading::GetCurrentThreadId},
     UI::{
         Shell::{
-            Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_MODIFY, NOTIFYICONDATAW,
+            DefSubclassProc, RemoveWindowSubclass, SHGetStockIconInfo, SetWindowSubclass,
+            Shell_NotifyIconGetRect, Shell_NotifyIconW, NIF_ICON, NIF_MESSAGE, NIF_SHOWTIP,
+            NIF_STATE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIM_SETVERSION, NIN_POPUPCLOSE,
+            NIN_POPUPOPEN, NIS_HIDDEN, NOTIFYICONDATAW, NOTIFYICONIDENTIFIER, NOTIFYICON_VERSION_4,
+            NOTIFY_ICON_MESSAGE, SHGSI_ICON, SHGSI_SMALLICON, SHSTOCKICONID, SHSTOCKICONINFO,
+            SIID_DELETE, SIID_ERROR, SIID_FIND, SIID_HELP, SIID_INFO, SIID_KEY, SIID_LOCK,
+            SIID_PRINTER, SIID_SHIELD, SIID_USERS, SIID_WARNING, SIID_WORLD,
         },
         WindowsAndMessaging::{
-            CreatePopupMenu, CreateWindowExW, DefWindowProcW, DestroyWindow, GetCursorPos,
-            LoadIconW, PostMessageW, PostQuitMessage, RegisterClassExW, RegisterClassW,
-            RegisterWindowMessageW, SetForegroundWindow, SetMenuInfo, CREATESTRUCTW, CS_HREDRAW,
-            CS_VREDRAW, CW_USEDEFAULT, GWL_USERDATA, HICON, IDI_APPLICATION, MENUINFO,
-            MIM_APPLYTOSUBMENUS, MIM_STYLE, MNS_NOTIFYBYPOS, WM_CREATE, WM_DESTROY,
-            WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK, WM_MBUTTONDOWN,
-            WM_MBUTTONUP, WM_MENUCOMMAND, WM_MOUSEMOVE, WM_NCCREATE, WM_RBUTTONDBLCLK,
-            WM_RBUTTONDOWN, WM_RBUTTONUP, WM_USER, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN, WM_XBUTTONUP,
-            WNDCLASSEXW, WNDCLASSW, WS_OVERLAPPEDWINDOW,
+            CopyIcon, CreateWindowExW, DefWindowProcW, DestroyWindow, FindWindowW, GetSystemMetrics,
+            GetWindowRect, IsWindow, IsWindowVisible, KillTimer, LoadIconW, PostMessageW,
+            PostQuitMessage, RegisterClassExW, RegisterClassW, RegisterWindowMessageW, SendMessageW,
+            SetForegroundWindow, SetTimer, CREATESTRUCTW, CS_HREDRAW, CS_VREDRAW, CW_USEDEFAULT,
+            GWL_USERDATA, HICON, HWND_MESSAGE, ICON_SMALL, ICON_SMALL2, IDI_APPLICATION,
+            SM_CXSMICON, SM_CYSMICON, WM_CREATE, WM_DESTROY, WM_DISPLAYCHANGE, WM_DPICHANGED,
+            WM_GETICON, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDBLCLK,
+            WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_NCCREATE, WM_NCDESTROY, WM_RBUTTONDBLCLK,
+            WM_RBUTTONDOWN, WM_RBUTTONUP, WM_SETTINGCHANGE, WM_TIMER, WM_USER, WM_XBUTTONDBLCLK,
+            WM_XBUTTONDOWN, WM_XBUTTONUP, WNDCLASSEXW, WNDCLASSW, WS_OVERLAPPEDWINDOW,
         },
     },
 };
 
 use crate::{
-    dpi::PhysicalPosition,
+    dpi::{PhysicalPosition, PhysicalSize},
     error::OsError as RootOsError,
     event::Event,
-    platform_impl::platform::{event_loop::ProcResult, WindowId, DEVICE_ID},
-    tray::TrayBuilder,
-    window::{Icon, WindowId as RootWindowId},
+    event_loop::EventLoopWindowTarget as RootEventLoopWindowTarget,
+    platform::windows::ShellIcon,
+    platform_impl::platform::{event_loop::ProcResult, WinIcon, Window, WindowId, DEVICE_ID},
+    tray::{
+        IconVisibility, PhysicalRect, StateKey, TrayBuilder, TrayDiagnostics, TrayError, TrayEvent,
+        TrayEventQueue, TrayId, WindowKind,
+    },
+    window::{BadIcon, Icon, Window as RootWindow, WindowBuilder, WindowId as RootWindowId},
 };
 
+use self::state::{BaseIcon, TrayState};
 use super::{
-    event_loop::{runner::EventLoopRunnerShared, DESTROY_MSG_ID},
+    dpi::get_monitor_dpi,
+    event_loop::{runner::EventLoopRunner, LazyMessageId},
     util, EventLoopWindowTarget,
 };
+#[cfg(feature = "tray-menu")]
+use {
+    self::menu::MenuState,
+    crate::tray::{MenuId, MenuTrigger},
+    windows_sys::Win32::{
+        Foundation::{ERROR_BUSY, ERROR_INVALID_PARAMETER},
+        UI::{
+            Shell::NIN_KEYSELECT,
+            WindowsAndMessaging::{
+                GetMenuItemID, HMENU, WM_CONTEXTMENU, WM_INITMENUPOPUP, WM_MENUCOMMAND,
+            },
+        },
+    },
+};
+#[cfg(feature = "tray-notifications")]
+use {
+    self::notification::ClickCallback,
+    crate::{
+        tray::{AlertOptions, NotificationBuilder, NotificationSetting},
+        window::UserAttentionType,
+    },
+    std::iter,
+    windows_sys::Win32::{
+        UI::{
+            Shell::{NIN_BALLOONHIDE, NIN_BALLOONSHOW, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK},
+            WindowsAndMessaging::{
+                FlashWindowEx, FLASHWINFO, FLASHW_ALL, FLASHW_TIMERNOFG, FLASHW_TRAY,
+            },
+        },
+    },
+};
+
+mod diagnostics;
+mod flyout;
+#[cfg(feature = "tray-menu")]
+mod menu;
+#[cfg(feature = "tray-notifications")]
+mod notification;
+mod orphans;
+mod persist;
+mod promotion;
+mod startup;
+mod state;
+mod tint;
+
+/// The message the shell notifies the window of the icon with, unless the builder chose another.
+const DEFAULT_CALLBACK_MESSAGE: u32 = WM_USER + 1;
+/// The messages free for applications to define, below the registered messages.
+const CALLBACK_MESSAGE_RANGE: std::ops::Range<u32> = WM_USER..0xC000;
+
+#[cfg(feature = "tray-menu")]
+const LONG_PRESS_TIMER_ID: usize = 1;
+#[cfg(feature = "tray-menu")]
+const LONG_PRESS_DELAY_MS: u32 = 500;
+const HOVER_TIMER_ID: usize = 2;
+const ICON_MOVED_TIMER_ID: usize = 3;
+/// How long the taskbar must be still before the position of the icon is checked, as moving it
+/// changes the settings several times.
+const ICON_MOVED_DELAY_MS: u32 = 200;
+/// How long a tooltip generated by the tooltip callback is shown before being generated again.
+const TOOLTIP_FN_INTERVAL: Duration = Duration::from_secs(1);
+/// The delay before retrying a shell call the first time, doubled for each retry.
+const SHELL_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The identifier of the subclass receiving the messages of adopted icons.
+const ADOPTED_SUBCLASS_ID: usize = 1;
+
+/// The class of the windows receiving the messages of the icons.
+pub const WINDOW_CLASS: &str = "Winit Tray Window";
+
+// Message sent by a `Tray` when its icon should be removed by the event loop thread. Distinct
+// from the one of windows, since an adopted icon may belong to a window of the event loop.
+// WPARAM and LPARAM are unused.
+static TRAY_DESTROY_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayDestroy\0");
+// WPARAM is a bool specifying whether the context menu is shown on right-click.
+#[cfg(feature = "tray-menu")]
+static SET_MENU_ENABLED_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TraySetMenuEnabled\0");
+// LPARAM is a `Box<Icon>` pointer, reclaimed by the callback.
+static SET_ICON_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TraySetIcon\0");
+// LPARAM is a `Box<Vec<u16>>` pointer to the encoded tooltip, reclaimed by the callback.
+static SET_TOOLTIP_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TraySetTooltip\0");
+// WPARAM is the `MenuId` of the item and LPARAM is a `&String` pointer to its label. Sent rather
+// than posted, returning 0 on success, `UNKNOWN_MENU_ITEM` or the OS error code.
+#[cfg(feature = "tray-menu")]
+static SET_ITEM_LABEL_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TraySetItemLabel\0");
+#[cfg(feature = "tray-menu")]
+const UNKNOWN_MENU_ITEM: LRESULT = -1;
+// LPARAM is a `&mut Option<PhysicalSize<u32>>` pointer, set to the estimated size of the menu.
+// Sent rather than posted.
+#[cfg(feature = "tray-menu")]
+static MENU_SIZE_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayMenuSize\0");
+// The message the shell notifies adopted icons with, which must not clash with the messages of
+// the window.
+static ADOPTED_CALLBACK_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayAdoptedCallback\0");
+// Sent from the thread of the window, returning whether its event loop is processing messages,
+// which it only does while running or pumping events.
+static IS_PUMP_ALIVE_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayIsPumpAlive\0");
+// LPARAM is a `&mut Option<ClickCallback>` pointer, swapped with the callback of the next balloon.
+// Sent rather than posted.
+#[cfg(feature = "tray-notifications")]
+static SWAP_BALLOON_CALLBACK_MSG_ID: LazyMessageId =
+    LazyMessageId::new("Winit::TraySwapBalloonCallback\0");
+// LPARAM is a `Box<TrayEvent>` pointer, reclaimed by the callback.
+#[cfg(feature = "tray-toast")]
+static TOAST_ACTION_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayToastAction\0");
+// Posted when the settings of the icons changed, which the shell doesn't notify, so that the user
+// hiding or showing the icon is reported. Passed on to the other icons adopted from the window.
+// WPARAM and LPARAM are unused.
+static SETTINGS_CHANGED_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TraySettingsChanged\0");
+// LPARAM is a `Box<TrayEvent>` pointer, reclaimed by the callback.
+#[cfg(all(feature = "tray-mock", debug_assertions))]
+static INJECT_EVENT_MSG_ID: LazyMessageId = LazyMessageId::new("Winit::TrayInjectEvent\0");
 
 #[derive(Clone)]
-pub struct Tray(HWND);
+pub struct Tray {
+    window: HWND,
+    id: TrayId,
+    /// The `uID` of the icon, which is also its `TrayId` unless the icon was adopted.
+    uid: u32,
+    /// The thread running the event loop, which created the window.
+    thread_id: u32,
+    state: Arc<Mutex<TrayState>>,
+    /// Whether the icon has a hover flyout, which replaces the tooltip.
+    has_hover_flyout: bool,
+    /// The window of the application given to the builder.
+    parent_window: Option<HWND>,
+    /// How many times shell calls failing transiently are retried.
+    shell_retries: u32,
+    /// Whether the icon was added by other code to a window we don't own, which is subclassed
+    /// instead.
+    adopted: bool,
+    /// Enables showing notifications as toasts.
+    #[cfg(feature = "tray-toast")]
+    app_user_model_id: Option<Arc<str>>,
+}
 
 impl Tray {
     pub fn new<T: 'static>(
         tray_builder: TrayBuilder,
         event_loop: &EventLoopWindowTarget<T>,
-    ) -> Result<Tray, RootOsError> {
-        let tray = init_window::<T>(tray_builder.parent_window, tray_builder.tooltip, event_loop)?;
-        if let Some(icon) = tray_builder.icon {
-            tray.set_icon(icon)?;
+    ) -> Result<Tray, TrayError> {
+        init_window::<T>(tray_builder, event_loop)
+    }
+
+    /// Takes over the icon `uid` of `window`, added by other code.
+    ///
+    /// # Safety
+    ///
+    /// `window` must be a window created on the thread of `event_loop`, and its icon `uid` must
+    /// not be modified or removed by other code afterwards.
+    pub unsafe fn from_raw<T: 'static>(
+        event_loop: &EventLoopWindowTarget<T>,
+        window: HWND,
+        uid: u32,
+        guid: Option<u128>,
+    ) -> Result<Tray, TrayError> {
+        adopt_icon(event_loop, window, uid, guid)
+    }
+
+    pub fn diagnose() -> TrayDiagnostics {
+        diagnostics::diagnose()
+    }
+
+    /// Returns a copy of the small icon of `window`, which stays valid whatever happens to the
+    /// window.
+    pub fn window_icon(window: &Window) -> Option<Icon> {
+        // `ICON_SMALL2` is the small icon derived from the big one, if only that one was set.
+        let handle = [ICON_SMALL, ICON_SMALL2]
+            .into_iter()
+            .map(|kind| unsafe { SendMessageW(window.hwnd(), WM_GETICON, kind as WPARAM, 0) })
+            .find(|&handle| handle != 0)?;
+        let copy = unsafe { CopyIcon(handle) };
+        if copy == 0 {
+            warn!("Failed to copy the window icon: {}", std::io::Error::last_os_error());
+            return None;
+        }
+        Some(Icon {
+            inner: WinIcon::from_handle(copy),
+        })
+    }
+
+    pub fn validate(tray_builder: &TrayBuilder) -> Vec<TrayError> {
+        let mut errors = Vec::new();
+        if let Some(tooltip) = &tray_builder.tooltip {
+            if encode_tooltip(tooltip).is_err() {
+                errors.push(TrayError::TooltipTooLong);
+            }
+        }
+        // An icon file is only loaded once the size of the icon is known, when building the tray.
+        if tray_builder.icon.is_none() && tray_builder.platform_specific.icon_path.is_none() {
+            if let Err(err) = load_default_icon(tray_builder.require_icon) {
+                errors.push(err);
+            }
+        }
+        if let Some(message) = tray_builder.platform_specific.callback_message {
+            if !CALLBACK_MESSAGE_RANGE.contains(&message) {
+                errors.push(TrayError::InvalidCallbackMessage(message));
+            }
         }
-        Ok(tray)
+        match tray_builder.parent_window {
+            Some(RawWindowHandle::Win32(handle))
+                if unsafe { IsWindow(handle.hwnd.get()) } != false.into() => {}
+            Some(_) => errors.push(TrayError::InvalidParentWindow),
+            None => (),
+        }
+        errors
+    }
+
+    pub fn set_run_at_startup(enabled: bool) -> Result<(), RootOsError> {
+        startup::set_run_at_startup(enabled).map_err(|err| os_error!(err))
+    }
+
+    pub fn runs_at_startup() -> bool {
+        startup::runs_at_startup()
+    }
+
+    pub fn cleanup_orphans(window_class: &str) -> Result<usize, RootOsError> {
+        orphans::cleanup(window_class).map_err(|err| os_error!(err))
     }
 
-    pub fn id(&self) -> RootWindowId {
+    pub fn id(&self) -> TrayId {
+        self.id
+    }
+
+    /// The window and `uID` the shell identifies the icon with.
+    pub fn icon_identifier(&self) -> (HWND, u32) {
+        (self.window, self.uid)
+    }
+
+    pub fn window_id(&self) -> RootWindowId {
         RootWindowId(WindowId(**self))
     }
 
-    pub fn set_icon(&self, icon: Icon) -> Result<(), RootOsError> {
-        let icon = icon.inner.as_raw_handle();
-        let mut icon_data = unsafe { std::mem::zeroed::<NOTIFYICONDATAW>() };
-        icon_data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
-        icon_data.hWnd = **self;
-        icon_data.uID = 1;
-        icon_data.uFlags = NIF_ICON;
-        icon_data.hIcon = icon;
+    #[cfg(feature = "tray-notifications")]
+    pub fn show_notification(
+        &self,
+        notification: NotificationBuilder,
+        on_click: Option<ClickCallback>,
+    ) -> Result<(), RootOsError> {
+        self.deliver_or_withhold(notification, on_click).map(|_| ())
+    }
 
-        unsafe {
-            if Shell_NotifyIconW(NIM_MODIFY, &icon_data) == 0 {
-                return Err(os_error!(std::io::Error::last_os_error()));
-            }
+    /// Delivers `notification`, unless it is withheld during quiet time. Returns whether it was
+    /// withheld, so that callers agree with the decision even if quiet time changes meanwhile.
+    #[cfg(feature = "tray-notifications")]
+    fn deliver_or_withhold(
+        &self,
+        notification: NotificationBuilder,
+        mut on_click: Option<ClickCallback>,
+    ) -> Result<bool, RootOsError> {
+        if notification.respect_quiet_time && notification::is_quiet_time() {
+            let mut state = self.state.lock().unwrap();
+            state.pending_notifications.push((notification, on_click));
+            return Ok(true);
+        }
+
+        self.deliver_notification(&notification, &mut on_click)?;
+        Ok(false)
+    }
+
+    #[cfg(feature = "tray-notifications")]
+    pub fn alert(
+        &self,
+        notification: NotificationBuilder,
+        options: AlertOptions,
+    ) -> Result<(), RootOsError> {
+        if self.deliver_or_withhold(notification, None)? {
+            return Ok(());
+        }
+
+        if let (Some(parent), Some(attention)) = (self.parent_window, options.attention) {
+            let (flags, count) = match attention {
+                UserAttentionType::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, u32::MAX),
+                UserAttentionType::Informational => (FLASHW_TRAY | FLASHW_TIMERNOFG, 0),
+            };
+            let flash_info = FLASHWINFO {
+                cbSize: mem::size_of::<FLASHWINFO>() as u32,
+                hwnd: parent,
+                dwFlags: flags,
+                uCount: count,
+                dwTimeout: 0,
+            };
+            unsafe { FlashWindowEx(&flash_info) };
+        }
+        if let Some(sound) = &options.sound {
+            notification::play_sound(sound);
         }
         Ok(())
     }
 
-    pub fn set_tooltip(&self, tooltip: &str) -> Result<(), RootOsError> {
-        let wide_tooltip = util::encode_wide(tooltip);
-        if wide_tooltip.len() > 128 {
-            // return Err("The tooltip may not exceed 127 wide bytes");
-            return Err(os_error!(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "The tooltip may not exceed 127 wide bytes"
-            )));
+    #[cfg(feature = "tray-notifications")]
+    pub fn pending_quiet_notifications(&self) -> usize {
+        self.state.lock().unwrap().pending_notifications.len()
+    }
+
+    #[cfg(feature = "tray-notifications")]
+    pub fn flush_quiet_notifications(&self) -> Result<usize, RootOsError> {
+        if notification::is_quiet_time() {
+            return Ok(0);
         }
 
-        let mut nid = unsafe { std::mem::zeroed::<NOTIFYICONDATAW>() };
-        nid.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
-        nid.hWnd = **self;
-        nid.uID = 1;
-        nid.uFlags = NIF_TIP;
+        let pending = mem::take(&mut self.state.lock().unwrap().pending_notifications);
+        let mut pending = pending.into_iter();
+        let mut delivered = 0;
+        while let Some((notification, mut on_click)) = pending.next() {
+            if let Err(err) = self.deliver_notification(&notification, &mut on_click) {
+                // Keeps the notifications which weren't delivered, ahead of those withheld since.
+                let mut state = self.state.lock().unwrap();
+                let withheld = mem::take(&mut state.pending_notifications);
+                state.pending_notifications = iter::once((notification, on_click))
+                    .chain(pending)
+                    .chain(withheld)
+                    .collect();
+                return Err(err);
+            }
+            delivered += 1;
+        }
+        Ok(delivered)
+    }
 
-        #[cfg(target_arch = "x86")]
-        {
-            let mut tip_data = [0u16; 128];
-            tip_data[..wide_tooltip.len()].copy_from_slice(&wide_tooltip);
-            nid.szTip = tip_data;
+    #[cfg(feature = "tray-notifications")]
+    /// Shows `notification`, taking `on_click` only if it succeeds.
+    fn deliver_notification(
+        &self,
+        notification: &NotificationBuilder,
+        on_click: &mut Option<ClickCallback>,
+    ) -> Result<(), RootOsError> {
+        #[cfg(feature = "tray-toast")]
+        if let Some(app_user_model_id) = &self.app_user_model_id {
+            let window = **self;
+            let tag = notification.tag.clone();
+            let on_action = move |action_id| {
+                let event = TrayEvent::NotificationAction {
+                    notification: tag.clone(),
+                    action_id,
+                };
+                if let Err(err) = post_boxed(window, TOAST_ACTION_MSG_ID.get(), event) {
+                    warn!("Failed to report a notification action: {err}");
+                }
+            };
+            // Clicking a toast activates the application through its own COM server, which we
+            // don't register, so `on_click` is never called.
+            return notification::show_toast(app_user_model_id, self.uid, notification, on_action)
+                .map(|()| drop(on_click.take()))
+                .map_err(|err| os_error!(std::io::Error::from(err)));
         }
 
-        #[cfg(not(target_arch = "x86"))]
-        nid.szTip[..wide_tooltip.len()].copy_from_slice(&wide_tooltip);
+        // The callback is registered first so that it can't miss a quick click.
+        self.swap_balloon_callback(on_click);
+        let result = notification::show_balloon(**self, self.uid, notification, self.shell_retries);
+        if result.is_err() {
+            self.swap_balloon_callback(on_click);
+        }
+        result
+    }
 
+    #[cfg(feature = "tray-notifications")]
+    /// Swaps `on_click` with the callback of the next balloon shown by the icon.
+    fn swap_balloon_callback(&self, on_click: &mut Option<ClickCallback>) {
         unsafe {
-            if Shell_NotifyIconW(NIM_MODIFY, &nid) == 0 {
-                return Err(os_error!(std::io::Error::last_os_error()));
+            SendMessageW(
+                **self,
+                SWAP_BALLOON_CALLBACK_MSG_ID.get(),
+                0,
+                on_click as *mut Option<ClickCallback> as LPARAM,
+            )
+        };
+    }
+
+    #[cfg(feature = "tray-notifications")]
+    pub fn notifications_enabled(&self) -> NotificationSetting {
+        #[cfg(feature = "tray-toast")]
+        if let Some(app_user_model_id) = &self.app_user_model_id {
+            return notification::toast_setting(app_user_model_id);
+        }
+
+        notification::balloon_setting()
+    }
+
+    #[cfg(feature = "tray-notifications")]
+    pub fn update_notification_progress(&self, tag: &str, value: f64) -> Result<(), RootOsError> {
+        #[cfg(feature = "tray-toast")]
+        if let Some(app_user_model_id) = &self.app_user_model_id {
+            return notification::update_toast_progress(app_user_model_id, self.uid, tag, value)
+                .map_err(|err| os_error!(std::io::Error::from(err)));
+        }
+
+        // Balloons don't have a progress bar.
+        let _ = (tag, value);
+        Ok(())
+    }
+
+    pub fn updater(&self) -> TrayUpdater {
+        TrayUpdater {
+            window: self.window,
+        }
+    }
+
+    /// Whether this is the thread of the window, which owns the state of the icon.
+    fn on_window_thread(&self) -> bool {
+        unsafe { GetCurrentThreadId() } == self.thread_id
+    }
+
+    pub fn set_icon(&self, icon: Icon) -> Result<(), RootOsError> {
+        if !self.on_window_thread() {
+            return post_boxed(**self, SET_ICON_MSG_ID.get(), icon);
+        }
+        self.set_icon_sync(icon).map_err(|err| match err {
+            TrayError::Os(err) | TrayError::ShellCallFailed(err) => err,
+            // Only a tinted version of the icon can fail to render.
+            TrayError::InvalidIcon(err) => {
+                os_error!(std::io::Error::new(std::io::ErrorKind::Other, err))
             }
+            err => unreachable!("Unexpected error updating the tray icon: {err}"),
+        })
+    }
+
+    pub fn set_icon_sync(&self, icon: Icon) -> Result<(), TrayError> {
+        if !self.on_window_thread() {
+            return Err(TrayError::WrongThread);
+        }
+        update_icon(**self, self.uid, &self.state, self.shell_retries, |state| {
+            state.set_icon(icon)
+        })
+    }
+
+    pub fn set_shell_icon(&self, icon: ShellIcon) -> Result<(), TrayError> {
+        let icon = load_shell_icon(icon).map_err(TrayError::InvalidIcon)?;
+        self.set_icon(icon).map_err(Into::into)
+    }
+
+    pub fn reveal(&self) -> Result<(), TrayError> {
+        if !self.on_window_thread() {
+            return Err(TrayError::WrongThread);
+        }
+        let mut nid = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+        nid.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+        nid.hWnd = **self;
+        nid.uID = self.uid;
+        nid.uFlags = NIF_STATE;
+        nid.dwState = 0;
+        nid.dwStateMask = NIS_HIDDEN;
+        shell_notify_retry(NIM_MODIFY, &nid, self.shell_retries)
+            .map_err(|err| TrayError::ShellCallFailed(os_error!(err)))
+    }
+
+    pub fn set_status(&self, key: StateKey) -> Result<(), TrayError> {
+        if !self.on_window_thread() {
+            return Err(TrayError::WrongThread);
+        }
+        if !self.state.lock().unwrap().has_status(key) {
+            return Err(TrayError::UnknownStatus(key));
+        }
+        update_icon(**self, self.uid, &self.state, self.shell_retries, |state| {
+            state.set_status(key)
+        })
+    }
+
+    pub fn tinted_icon(icon: &Icon, color: [u8; 4]) -> Result<Icon, BadIcon> {
+        let inner = tint::tint_icon(icon.inner.as_raw_handle(), color)?;
+        Ok(Icon { inner })
+    }
+
+    pub fn set_icon_tint(&self, tint: Option<[u8; 4]>) -> Result<(), TrayError> {
+        if !self.on_window_thread() {
+            return Err(TrayError::WrongThread);
+        }
+        update_icon(**self, self.uid, &self.state, self.shell_retries, |state| {
+            state.set_tint(tint)
+        })
+    }
+
+    #[cfg(all(feature = "tray-mock", debug_assertions))]
+    pub fn inject_event(&self, event: TrayEvent) -> Result<(), RootOsError> {
+        post_boxed(**self, INJECT_EVENT_MSG_ID.get(), event)
+    }
+
+    #[cfg(feature = "tray-menu")]
+    pub fn set_item_label(&self, id: MenuId, label: &str) -> Result<(), TrayError> {
+        let label = label.to_string();
+        let result = unsafe {
+            SendMessageW(
+                **self,
+                SET_ITEM_LABEL_MSG_ID.get(),
+                id.0 as WPARAM,
+                &label as *const String as LPARAM,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            UNKNOWN_MENU_ITEM => Err(TrayError::UnknownMenuItem(id)),
+            error => Err(os_error!(std::io::Error::from_raw_os_error(error as i32)).into()),
         }
+    }
+
+    #[cfg(feature = "tray-menu")]
+    pub fn menu_size(&self) -> Option<PhysicalSize<u32>> {
+        let mut size = None;
+        unsafe {
+            SendMessageW(
+                **self,
+                MENU_SIZE_MSG_ID.get(),
+                0,
+                &mut size as *mut Option<PhysicalSize<u32>> as LPARAM,
+            )
+        };
+        size
+    }
+
+    #[cfg(feature = "tray-menu")]
+    pub fn set_menu_enabled(&self, enabled: bool) {
+        unsafe { PostMessageW(**self, SET_MENU_ENABLED_MSG_ID.get(), enabled as WPARAM, 0) };
+    }
+
+    pub fn recommended_icon_size(&self) -> PhysicalSize<u32> {
+        recommended_icon_size(**self, self.uid)
+    }
+
+    pub fn get_rect(&self) -> Option<PhysicalRect> {
+        physical_icon_rect(**self, self.uid)
+    }
+
+    pub fn anchor_window(&self, window: &Window) -> Result<(), RootOsError> {
+        let position = anchored_position(**self, self.uid, window.outer_size())?;
+        window.set_hide_on_deactivate(true);
+        window.set_outer_position(position.into());
+        window.set_visible(true);
+        window.focus_window();
         Ok(())
     }
+
+    pub fn set_tooltip(&self, tooltip: &str) -> Result<(), RootOsError> {
+        let wide_tooltip = encode_tooltip(tooltip)?;
+        if !self.on_window_thread() {
+            return post_boxed(**self, SET_TOOLTIP_MSG_ID.get(), wide_tooltip);
+        }
+        let show_tip = !self.has_hover_flyout;
+        modify_tooltip(**self, self.uid, &wide_tooltip, show_tip, self.shell_retries)
+    }
+
+    pub fn set_tooltip_sync(&self, tooltip: &str) -> Result<(), TrayError> {
+        if !self.on_window_thread() {
+            return Err(TrayError::WrongThread);
+        }
+        let show_tip = !self.has_hover_flyout;
+        let wide_tooltip = encode_tooltip(tooltip)?;
+        modify_tooltip(**self, self.uid, &wide_tooltip, show_tip, self.shell_retries)
+            .map_err(TrayError::Os)
+    }
 }
 
 unsafe impl Send for Tray {}
@@ -112,24 +623,324 @@ unsafe impl Sync for Tray {}
 
 impl Drop for Tray {
     fn drop(&mut self) {
-        unsafe {
-            // The window must be destroyed from the same thread that created it, so we send a
-            // custom message to be handled by our callback to do the actual work.
-            PostMessageW(self.0, DESTROY_MSG_ID.get(), 0, 0);
+        let on_window_thread = self.on_window_thread();
+        let destroy_msg = TRAY_DESTROY_MSG_ID.get();
+        if self.adopted {
+            // Only the icon and subclass are removed, by the callback so that it releases its
+            // state along with them.
+            if on_window_thread {
+                unsafe { SendMessageW(self.window, destroy_msg, 0, 0) };
+            } else if unsafe { PostMessageW(self.window, destroy_msg, 0, 0) } == false.into() {
+                delete_icon(self.window, self.uid, self.shell_retries);
+            }
+            return;
+        }
+        // A message sent from the thread of the window is handled right away, so this works even
+        // once the event loop stopped pumping messages.
+        if on_window_thread
+            && unsafe { SendMessageW(self.window, IS_PUMP_ALIVE_MSG_ID.get(), 0, 0) } == 0
+        {
+            // A posted message would wait for the event loop to pump again, which may never happen.
+            if unsafe { DestroyWindow(self.window) } == false.into() {
+                delete_icon(self.window, self.uid, self.shell_retries);
+            }
+            return;
+        }
+
+        // The window must be destroyed from the same thread that created it, so we send a
+        // custom message to be handled by our callback to do the actual work.
+        if unsafe { PostMessageW(self.window, destroy_msg, 0, 0) } == false.into() {
+            if on_window_thread {
+                unsafe { DestroyWindow(self.window) };
+            } else {
+                // The window is likely gone with its thread, which doesn't remove the icon.
+                delete_icon(self.window, self.uid, self.shell_retries);
+                warn!("Failed to destroy the tray window from another thread, it may leak");
+            }
+        }
+    }
+}
+
+/// Applies updates to a tray icon from any thread by posting them to the window of the icon.
+///
+/// Unlike `Tray`, this only holds the window handle, which is plain data, so it is `Send` and
+/// `Sync` without any `unsafe impl`.
+#[derive(Clone)]
+pub struct TrayUpdater {
+    window: HWND,
+}
+
+impl TrayUpdater {
+    pub fn set_icon(&self, icon: Icon) -> Result<(), RootOsError> {
+        post_boxed(self.window, SET_ICON_MSG_ID.get(), icon)
+    }
+
+    pub fn set_tooltip(&self, tooltip: &str) -> Result<(), RootOsError> {
+        let wide_tooltip = encode_tooltip(tooltip)?;
+        post_boxed(self.window, SET_TOOLTIP_MSG_ID.get(), wide_tooltip)
+    }
+}
+
+/// Posts `msg` to `window` with a heap allocated `payload` as its `LPARAM`.
+///
+/// The callback takes back ownership of the payload with `Box::from_raw`. If the window is
+/// destroyed before the message is processed, the payload is leaked.
+fn post_boxed<P>(window: HWND, msg: u32, payload: P) -> Result<(), RootOsError> {
+    let payload = Box::into_raw(Box::new(payload));
+    if unsafe { PostMessageW(window, msg, 0, payload as LPARAM) } == false.into() {
+        drop(unsafe { Box::from_raw(payload) });
+        return Err(os_error!(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Applies `f` to the state of the icon, then shows the resulting icon.
+fn update_icon<F>(
+    window: HWND,
+    uid: u32,
+    state: &Mutex<TrayState>,
+    retries: u32,
+    f: F,
+) -> Result<(), TrayError>
+where
+    F: FnOnce(&mut TrayState),
+{
+    // The lock is held until the shell made its own copy of the icon, as a tinted icon is
+    // destroyed when the cache of the state is cleared.
+    let mut state = state.lock().unwrap();
+    f(&mut state);
+    let icon = state.displayed_icon().map_err(TrayError::InvalidIcon)?;
+    modify_icon(window, uid, icon, retries)?;
+    if let Some((name, status)) = state.unsaved_status() {
+        if let Err(err) = persist::save_status(name, status) {
+            warn!("Failed to save the tray status: {err}");
         }
     }
+    Ok(())
+}
+
+fn modify_icon(window: HWND, uid: u32, icon: HICON, retries: u32) -> Result<(), TrayError> {
+    let mut icon_data = unsafe { std::mem::zeroed::<NOTIFYICONDATAW>() };
+    icon_data.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
+    icon_data.hWnd = window;
+    icon_data.uID = uid;
+    icon_data.uFlags = NIF_ICON;
+    icon_data.hIcon = icon;
+
+    shell_notify_retry(NIM_MODIFY, &icon_data, retries)
+        .map_err(|err| TrayError::ShellCallFailed(os_error!(err)))?;
+    Ok(())
+}
+
+fn physical_icon_rect(window: HWND, uid: u32) -> Option<PhysicalRect> {
+    icon_rect(window, uid).map(|rect| PhysicalRect {
+        position: PhysicalPosition::new(rect.left, rect.top),
+        size: PhysicalSize::new(
+            (rect.right - rect.left) as u32,
+            (rect.bottom - rect.top) as u32,
+        ),
+    })
+}
+
+/// Returns the size of the icon `uid` of `window` for the DPI of the monitor showing it.
+fn recommended_icon_size(window: HWND, uid: u32) -> PhysicalSize<u32> {
+    let monitor = hosting_monitor(window, uid);
+    let metrics = match (*util::GET_SYSTEM_METRICS_FOR_DPI, get_monitor_dpi(monitor)) {
+        (Some(get_system_metrics_for_dpi), Some(dpi)) => unsafe {
+            (
+                get_system_metrics_for_dpi(SM_CXSMICON, dpi),
+                get_system_metrics_for_dpi(SM_CYSMICON, dpi),
+            )
+        },
+        // Before Windows 10 Anniversary Update the metrics are only reported for the
+        // system DPI, which is the best we can do there.
+        _ => unsafe { (GetSystemMetrics(SM_CXSMICON), GetSystemMetrics(SM_CYSMICON)) },
+    };
+    PhysicalSize::new(metrics.0 as u32, metrics.1 as u32)
+}
+
+fn hosting_monitor(window: HWND, uid: u32) -> HMONITOR {
+    unsafe {
+        match icon_rect(window, uid) {
+            Some(rect) => MonitorFromRect(&rect, MONITOR_DEFAULTTOPRIMARY),
+            // An icon in the overflow area lives on the primary taskbar.
+            None => MonitorFromPoint(POINT { x: 0, y: 0 }, MONITOR_DEFAULTTOPRIMARY),
+        }
+    }
+}
+
+/// Returns the position of a window of `size` placed next to the icon `uid` of `window`.
+fn anchored_position(
+    window: HWND,
+    uid: u32,
+    size: PhysicalSize<u32>,
+) -> Result<PhysicalPosition<i32>, RootOsError> {
+    let icon = match icon_rect(window, uid) {
+        Some(rect) => rect,
+        // The icon is in the overflow area, which is usually where the user just clicked.
+        None => {
+            let point = util::cursor_position()
+                .ok_or_else(|| os_error!(std::io::Error::last_os_error()))?
+                .cast::<i32>();
+            RECT {
+                left: point.x,
+                top: point.y,
+                right: point.x + 1,
+                bottom: point.y + 1,
+            }
+        }
+    };
+
+    let mut monitor_info = unsafe { mem::zeroed::<MONITORINFO>() };
+    monitor_info.cbSize = mem::size_of::<MONITORINFO>() as u32;
+    let monitor = unsafe { MonitorFromRect(&icon, MONITOR_DEFAULTTONEAREST) };
+    if unsafe { GetMonitorInfoW(monitor, &mut monitor_info) } == false.into() {
+        return Err(os_error!(std::io::Error::last_os_error()));
+    }
+
+    Ok(flyout::flyout_position(
+        &icon,
+        &monitor_info.rcMonitor,
+        &monitor_info.rcWork,
+        size,
+    ))
+}
+
+/// Calls `Shell_NotifyIconW`, retrying up to `retries` times with an increasing delay while the
+/// shell is too busy to respond, e.g. around login or while Explorer restarts.
+fn shell_notify_retry(
+    message: NOTIFY_ICON_MESSAGE,
+    data: &NOTIFYICONDATAW,
+    retries: u32,
+) -> Result<(), std::io::Error> {
+    let mut delay = SHELL_RETRY_DELAY;
+    let mut attempt = 0;
+    loop {
+        if unsafe { Shell_NotifyIconW(message, data) } != 0 {
+            return Ok(());
+        }
+        let err = std::io::Error::last_os_error();
+        // Only a timeout is transient, other errors come from the call itself.
+        if attempt == retries || err.raw_os_error() != Some(ERROR_TIMEOUT as i32) {
+            return Err(err);
+        }
+        thread::sleep(delay);
+        delay *= 2;
+        attempt += 1;
+    }
+}
+
+/// Removes the icon `uid` of `window`, returning whether the shell still showed it.
+fn delete_icon(window: HWND, uid: u32, retries: u32) -> bool {
+    let mut nid = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+    nid.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    nid.hWnd = window;
+    nid.uID = uid;
+    let deleted = shell_notify_retry(NIM_DELETE, &nid, retries).is_ok();
+    orphans::forget(window, uid);
+    deleted
+}
+
+/// Returns the rectangle of the icon `uid` of `window`, or `None` if it isn't visible.
+fn icon_rect(window: HWND, uid: u32) -> Option<RECT> {
+    let mut identifier = unsafe { mem::zeroed::<NOTIFYICONIDENTIFIER>() };
+    identifier.cbSize = mem::size_of::<NOTIFYICONIDENTIFIER>() as u32;
+    identifier.hWnd = window;
+    identifier.uID = uid;
+
+    let mut rect = unsafe { mem::zeroed::<RECT>() };
+    if unsafe { Shell_NotifyIconGetRect(&identifier, &mut rect) } == S_OK {
+        Some(rect)
+    } else {
+        None
+    }
+}
+
+fn encode_tooltip(tooltip: &str) -> Result<Vec<u16>, RootOsError> {
+    let wide_tooltip = util::encode_wide(tooltip);
+    if wide_tooltip.len() > 128 {
+        return Err(os_error!(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "The tooltip may not exceed 127 wide bytes"
+        )));
+    }
+    Ok(wide_tooltip)
+}
+
+/// Encodes `tooltip` like `encode_tooltip`, truncating it instead of failing when it is too long.
+fn encode_tooltip_truncated(tooltip: &str) -> Vec<u16> {
+    let mut wide_tooltip: Vec<u16> = tooltip.encode_utf16().take(127).collect();
+    // Doesn't leave half of a surrogate pair.
+    if wide_tooltip.len() == 127 && matches!(wide_tooltip[126], 0xD800..=0xDBFF) {
+        wide_tooltip.pop();
+    }
+    wide_tooltip.push(0);
+    wide_tooltip
+}
+
+/// Copies an encoded tooltip into a zeroed `szTip` buffer, so that nothing of a previous tooltip
+/// is left after it and the buffer is always null-terminated.
+///
+/// The buffer is assigned as a whole because `NOTIFYICONDATAW` is packed on x86, where its
+/// fields can't be borrowed.
+fn tip_buffer(wide_tooltip: &[u16]) -> [u16; 128] {
+    let mut tip = [0u16; 128];
+    let len = wide_tooltip.len().min(tip.len() - 1);
+    tip[..len].copy_from_slice(&wide_tooltip[..len]);
+    tip
+}
+
+/// Sets the tooltip of the icon. Unless `show_tip` is set, the shell sends `NIN_POPUPOPEN` and
+/// `NIN_POPUPCLOSE` instead of showing it.
+fn modify_tooltip(
+    window: HWND,
+    uid: u32,
+    wide_tooltip: &[u16],
+    show_tip: bool,
+    retries: u32,
+) -> Result<(), RootOsError> {
+    let mut nid = unsafe { std::mem::zeroed::<NOTIFYICONDATAW>() };
+    nid.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
+    nid.hWnd = window;
+    nid.uID = uid;
+    nid.uFlags = NIF_TIP;
+    if show_tip {
+        nid.uFlags |= NIF_SHOWTIP;
+    }
+
+    nid.szTip = tip_buffer(wide_tooltip);
+
+    shell_notify_retry(NIM_MODIFY, &nid, retries).map_err(|err| os_error!(err))
 }
 
 impl Deref for Tray {
     type Target = HWND;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.window
     }
 }
 
 pub struct InitData<'a, T: 'static> {
     pub event_loop: &'a EventLoopWindowTarget<T>,
+    pub tray_id: TrayId,
+    pub uid: u32,
+    pub tray_state: Arc<Mutex<TrayState>>,
+    #[cfg(feature = "tray-menu")]
+    pub menu: Option<MenuState>,
+    pub primary_action: Option<Box<dyn Fn()>>,
+    #[cfg(feature = "tray-menu")]
+    pub primary_item: Option<MenuId>,
+    pub tooltip_fn: Option<Box<dyn Fn() -> String>>,
+    #[cfg(feature = "tray-menu")]
+    pub menu_trigger: MenuTrigger,
+    pub hover_flyout: Option<HoverFlyout>,
+    pub shell_retries: u32,
+    pub emit_cursor_moved: bool,
+    pub event_queue: Option<TrayEventQueue>,
+    pub event_sender: Option<Sender<TrayEvent>>,
+    pub callback_message: u32,
+    pub adopted: bool,
     // outputs
     pub window: Option<HWND>,
 }
@@ -140,7 +951,40 @@ impl<'a, T: 'static> InitData<'a, T> {
 
         let result = runner.catch_unwind(|| {
             let window_data = WindowData {
-                event_loop_runner: self.event_loop.runner_shared.clone(),
+                event_loop_runner: Rc::downgrade(&self.event_loop.runner_shared),
+                tray_id: self.tray_id,
+                uid: self.uid,
+                tray_state: self.tray_state.clone(),
+                #[cfg(feature = "tray-menu")]
+                menu: RefCell::new(self.menu.take()),
+                #[cfg(feature = "tray-menu")]
+                menu_enabled: Cell::new(true),
+                primary_action: self.primary_action.take(),
+                #[cfg(feature = "tray-menu")]
+                primary_item: self.primary_item,
+                tooltip_fn: self.tooltip_fn.take(),
+                tooltip_generated: Cell::new(None),
+                #[cfg(feature = "tray-menu")]
+                menu_trigger: self.menu_trigger,
+                #[cfg(feature = "tray-menu")]
+                long_pressed: Cell::new(false),
+                hover_flyout: self.hover_flyout.take(),
+                shell_retries: self.shell_retries,
+                emit_cursor_moved: self.emit_cursor_moved,
+                event_queue: self.event_queue.take(),
+                event_sender: self.event_sender.take(),
+                callback_message: self.callback_message,
+                adopted: self.adopted,
+                shell_version: Cell::new(0),
+                visibility: Cell::new(None),
+                #[cfg(feature = "tray-menu")]
+                right_clicked: Cell::new(false),
+                #[cfg(feature = "tray-menu")]
+                menu_visible: Cell::new(false),
+                #[cfg(feature = "tray-notifications")]
+                balloon_callback: Cell::new(None),
+                #[cfg(feature = "tray-notifications")]
+                next_balloon_callback: Cell::new(None),
                 userdata_removed: Cell::new(false),
                 recurse_depth: Cell::new(0),
             };
@@ -154,28 +998,376 @@ impl<'a, T: 'static> InitData<'a, T> {
     }
 }
 
+pub(crate) struct HoverFlyout {
+    delay: Duration,
+    builder: Box<dyn Fn() -> WindowBuilder>,
+    /// The window while it is shown, dropped once the cursor leaves the icon.
+    window: RefCell<Option<RootWindow>>,
+}
+
+impl HoverFlyout {
+    /// Builds the window of the flyout and shows it next to the icon `uid` of `window`.
+    fn show<T: 'static>(&self, window: HWND, uid: u32, runner: Rc<EventLoopRunner<T>>) {
+        let target = RootEventLoopWindowTarget {
+            p: EventLoopWindowTarget::from_runner(runner),
+            _marker: PhantomData,
+        };
+        // Built inactive, so that it is shown with `SW_SHOWNOACTIVATE` and the foreground
+        // application keeps the focus.
+        let builder = (self.builder)().with_visible(false).with_active(false);
+        let flyout = match builder.build(&target) {
+            Ok(flyout) => flyout,
+            Err(err) => {
+                warn!("Failed to create the tray hover flyout: {err}");
+                return;
+            }
+        };
+        match anchored_position(window, uid, flyout.outer_size()) {
+            Ok(position) => {
+                flyout.set_outer_position(position);
+                flyout.set_visible(true);
+                *self.window.borrow_mut() = Some(flyout);
+            }
+            Err(err) => warn!("Failed to position the tray hover flyout: {err}"),
+        }
+    }
+
+    /// Destroys the window of the flyout, if it is shown.
+    fn hide(&self) {
+        self.window.borrow_mut().take();
+    }
+}
+
 pub(crate) struct WindowData<T: 'static> {
-    pub event_loop_runner: EventLoopRunnerShared<T>,
+    /// Weak so that the runner is released along with the event loop.
+    pub event_loop_runner: Weak<EventLoopRunner<T>>,
+    pub tray_id: TrayId,
+    /// The `uID` the shell identifies the icon with.
+    pub uid: u32,
+    pub tray_state: Arc<Mutex<TrayState>>,
+    #[cfg(feature = "tray-menu")]
+    pub menu: RefCell<Option<MenuState>>,
+    #[cfg(feature = "tray-menu")]
+    pub menu_enabled: Cell<bool>,
+    pub primary_action: Option<Box<dyn Fn()>>,
+    /// The menu item chosen by left clicks.
+    #[cfg(feature = "tray-menu")]
+    pub primary_item: Option<MenuId>,
+    pub tooltip_fn: Option<Box<dyn Fn() -> String>>,
+    /// When `tooltip_fn` was last called.
+    pub tooltip_generated: Cell<Option<Instant>>,
+    #[cfg(feature = "tray-menu")]
+    pub menu_trigger: MenuTrigger,
+    /// Whether the left button being held opened the menu, in which case releasing it isn't a
+    /// click.
+    #[cfg(feature = "tray-menu")]
+    pub long_pressed: Cell<bool>,
+    pub hover_flyout: Option<HoverFlyout>,
+    pub shell_retries: u32,
+    /// Whether a `CursorMoved` precedes each `MouseInput` of the tray window.
+    pub emit_cursor_moved: bool,
+    /// Where the tray events go instead of the event loop.
+    pub event_queue: Option<TrayEventQueue>,
+    /// Where the tray events go instead of the event loop or the queue.
+    pub event_sender: Option<Sender<TrayEvent>>,
+    /// The message the shell sends the notifications of the icon with.
+    pub callback_message: u32,
+    /// Whether the window belongs to other code and is subclassed, in which case the messages
+    /// that aren't ours are passed on to it.
+    pub adopted: bool,
+    /// The notification version negotiated with the shell, either `NOTIFYICON_VERSION_4` or 0
+    /// for the legacy behavior of older shells.
+    pub shell_version: Cell<u32>,
+    /// Whether the icon was last seen on the taskbar or in the overflow area, if known.
+    pub visibility: Cell<Option<IconVisibility>>,
+    /// Whether the icon was right clicked since the last `WM_CONTEXTMENU`.
+    #[cfg(feature = "tray-menu")]
+    pub right_clicked: Cell<bool>,
+    #[cfg(feature = "tray-menu")]
+    pub menu_visible: Cell<bool>,
+    /// The click callback of the balloon being shown, and of the next one. The latter becomes
+    /// the former on `NIN_BALLOONSHOW`, so that the notifications of a balloon being replaced
+    /// don't affect the callback of its replacement.
+    #[cfg(feature = "tray-notifications")]
+    pub balloon_callback: Cell<Option<ClickCallback>>,
+    #[cfg(feature = "tray-notifications")]
+    pub next_balloon_callback: Cell<Option<ClickCallback>>,
     pub userdata_removed: Cell<bool>,
     pub recurse_depth: Cell<u32>,
 }
 impl<T> WindowData<T> {
     fn send_event(&self, event: Event<T>) {
-        self.event_loop_runner.send_event(event);
+        if let Some(runner) = self.event_loop_runner.upgrade() {
+            runner.send_event(event);
+        }
     }
+
+    fn send_tray_event(&self, event: TrayEvent) {
+        if let Some(sender) = &self.event_sender {
+            // The receiver being dropped only means that the application lost interest.
+            let _ = sender.send(event);
+            return;
+        }
+        if let Some(queue) = &self.event_queue {
+            queue.push(self.tray_id, event);
+            return;
+        }
+        self.send_event(Event::TrayEvent {
+            tray_id: self.tray_id,
+            event,
+        });
+    }
+
+    /// Removes the icon and releases everything held for it.
+    fn release(&self, window: HWND) {
+        delete_icon(window, self.uid, self.shell_retries);
+        LIVE_TRAYS
+            .lock()
+            .unwrap()
+            .retain(|&(id, _, _)| id != self.tray_id);
+
+        #[cfg(feature = "tray-menu")]
+        drop(self.menu.take());
+        self.tray_state.lock().unwrap().release();
+    }
+
+    /// Checks where the user chose to show the icon, reporting it if that changed since the last
+    /// check.
+    fn update_visibility(&self) {
+        let visibility = match promotion::current_exe_promoted() {
+            Ok(promoted) => promoted.map(|promoted| {
+                if promoted {
+                    IconVisibility::Visible
+                } else {
+                    IconVisibility::Hidden
+                }
+            }),
+            Err(err) => {
+                debug!("Failed to read the visibility of the tray icon: {err}");
+                return;
+            }
+        };
+        // The first known visibility isn't a change.
+        if let (Some(previous), Some(visibility)) = (self.visibility.get(), visibility) {
+            if previous != visibility {
+                self.send_tray_event(TrayEvent::VisibilityChanged(visibility));
+            }
+        }
+        if visibility.is_some() {
+            self.visibility.set(visibility);
+        }
+    }
+
+    /// Shows the menu of the tray at `position`, unless it is disabled or already shown.
+    #[cfg(feature = "tray-menu")]
+    fn show_menu(&self, window: HWND, position: PhysicalPosition<i32>, from_keyboard: bool) {
+        // The borrow must not be held while the menu is shown, as its modal loop dispatches
+        // `WM_INITMENUPOPUP` back to us.
+        let hmenu = self.menu.borrow().as_ref().map(MenuState::hmenu);
+        if let (Some(hmenu), true, false) =
+            (hmenu, self.menu_enabled.get(), self.menu_visible.get())
+        {
+            self.menu_visible.set(true);
+            menu::show_menu(window, hmenu, position, from_keyboard);
+            self.menu_visible.set(false);
+        }
+    }
+}
+
+/// Allocates the `uID` of a new icon. Icons are identified by both their window and `uID`, but
+/// keeping the latter unique in the process lets it serve as the `TrayId` on its own. Adopted
+/// icons keep their `uID` and take their `TrayId` from here instead.
+fn next_uid() -> u32 {
+    static NEXT_UID: AtomicU32 = AtomicU32::new(1);
+    NEXT_UID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// The classes of the windows showing the icons that don't fit on the taskbar, on Windows 10
+/// and 11 respectively.
+const OVERFLOW_WINDOW_CLASSES: [&str; 2] = [
+    "NotifyIconOverflowWindow",
+    "TopLevelWindowForOverflowXamlIsland",
+];
+
+/// Whether `point` lies within the overflow area of the taskbar while it is shown.
+fn is_in_overflow(point: PhysicalPosition<i32>) -> bool {
+    OVERFLOW_WINDOW_CLASSES.iter().any(|class| {
+        let class = util::encode_wide(class);
+        let window = unsafe { FindWindowW(class.as_ptr(), std::ptr::null()) };
+        let mut rect = unsafe { mem::zeroed::<RECT>() };
+        window != 0
+            && unsafe { IsWindowVisible(window) } != false.into()
+            && unsafe { GetWindowRect(window, &mut rect) } != false.into()
+            && (rect.left..rect.right).contains(&point.x)
+            && (rect.top..rect.bottom).contains(&point.y)
+    })
+}
+
+/// Loads the small version of the stock shell icon `icon`.
+fn load_shell_icon(icon: ShellIcon) -> Result<Icon, BadIcon> {
+    let id: SHSTOCKICONID = match icon {
+        ShellIcon::Info => SIID_INFO,
+        ShellIcon::Warning => SIID_WARNING,
+        ShellIcon::Error => SIID_ERROR,
+        ShellIcon::Help => SIID_HELP,
+        ShellIcon::Shield => SIID_SHIELD,
+        ShellIcon::Lock => SIID_LOCK,
+        ShellIcon::Key => SIID_KEY,
+        ShellIcon::Delete => SIID_DELETE,
+        ShellIcon::Find => SIID_FIND,
+        ShellIcon::World => SIID_WORLD,
+        ShellIcon::Users => SIID_USERS,
+        ShellIcon::Printer => SIID_PRINTER,
+    };
+    let mut info = unsafe { mem::zeroed::<SHSTOCKICONINFO>() };
+    info.cbSize = mem::size_of::<SHSTOCKICONINFO>() as u32;
+    // Icons introduced by later versions of Windows are rejected as invalid arguments.
+    let result = unsafe { SHGetStockIconInfo(id, SHGSI_ICON | SHGSI_SMALLICON, &mut info) };
+    if result != S_OK {
+        return Err(BadIcon::OsError(std::io::Error::from_raw_os_error(result)));
+    }
+    Ok(Icon {
+        inner: WinIcon::from_handle(info.hIcon),
+    })
+}
+
+/// Loads the frame of the `.ico` file at `path` closest to `size`, scaling it if needed.
+pub(crate) fn load_icon_file(path: &Path, size: PhysicalSize<u32>) -> Result<Icon, BadIcon> {
+    WinIcon::from_path(path, Some(size)).map(|inner| Icon { inner })
+}
+
+/// The window and `uID` of each live icon, by `TrayId`.
+static LIVE_TRAYS: Mutex<Vec<(TrayId, HWND, u32)>> = Mutex::new(Vec::new());
+
+/// Has every live icon check whether the user hid or showed it, from the thread watching the
+/// settings of the icons.
+fn post_settings_changed() {
+    let mut windows: Vec<HWND> = LIVE_TRAYS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|&(_, hwnd, _)| hwnd)
+        .collect();
+    // Icons adopted from the same window pass the message on to each other.
+    windows.sort_unstable();
+    windows.dedup();
+    for window in windows {
+        unsafe { PostMessageW(window, SETTINGS_CHANGED_MSG_ID.get(), 0, 0) };
+    }
+}
+
+/// Returns the tray whose icon belongs to the window `window_id`, if it is still alive.
+pub fn tray_id_from_window(window_id: WindowId) -> Option<TrayId> {
+    let live_trays = LIVE_TRAYS.lock().unwrap();
+    let &(id, _, _) = live_trays.iter().find(|&&(_, hwnd, _)| hwnd == window_id.0)?;
+    Some(id)
+}
+
+/// Returns the window of the tray `id`, if it is still alive.
+pub fn window_from_tray_id(id: TrayId) -> Option<WindowId> {
+    let live_trays = LIVE_TRAYS.lock().unwrap();
+    let &(_, hwnd, _) = live_trays.iter().find(|&&(live, _, _)| live == id)?;
+    Some(WindowId(hwnd))
+}
+
+/// Allocates the `TrayId` of the icon `uid` of `window` added by other code, or returns `None`
+/// if the icon is already live.
+///
+/// The `uID` of the icon isn't used as the `TrayId`, since [`next_uid`] may also give it out.
+fn adopted_tray_id(window: HWND, uid: u32) -> Option<TrayId> {
+    let live_trays = LIVE_TRAYS.lock().unwrap();
+    if live_trays
+        .iter()
+        .any(|&(_, hwnd, live)| hwnd == window && live == uid)
+    {
+        return None;
+    }
+    Some(TrayId(next_uid()))
+}
+
+/// Loads the icon used when none was given to the builder, which is the `tray-default` resource of
+/// the executable or, unless `require_icon` is set, the generic application icon.
+fn load_default_icon(require_icon: bool) -> Result<HICON, TrayError> {
+    let handle = unsafe {
+        LoadIconW(
+            GetModuleHandleW(std::ptr::null()),
+            util::encode_wide("tray-default").as_ptr(),
+        )
+    };
+    if handle != 0 {
+        return Ok(handle);
+    }
+    if require_icon {
+        return Err(TrayError::InvalidIcon(BadIcon::OsError(
+            std::io::Error::last_os_error(),
+        )));
+    }
+
+    let handle = unsafe { LoadIconW(0, IDI_APPLICATION) };
+    if handle == 0 {
+        return Err(os_error!(std::io::Error::last_os_error()).into());
+    }
+    Ok(handle)
 }
 
 pub fn init_window<T: 'static>(
-    parent_window: Option<RawWindowHandle>,
-    tooltip: Option<String>,
+    tray_builder: TrayBuilder,
     event_loop: &EventLoopWindowTarget<T>,
-) -> Result<Tray, RootOsError> {
+) -> Result<Tray, TrayError> {
+    #[cfg_attr(not(feature = "tray-toast"), allow(unused_variables))]
+    let TrayBuilder {
+        icon: custom_icon,
+        tooltip,
+        tooltip_fn,
+        parent_window,
+        #[cfg(feature = "tray-menu")]
+        menu,
+        require_icon,
+        prefer_promoted,
+        start_hidden,
+        primary_action,
+        #[cfg(feature = "tray-menu")]
+        primary_item,
+        #[cfg(feature = "tray-menu")]
+        menu_trigger,
+        window_kind,
+        hover_flyout,
+        shell_retries,
+        emit_cursor_moved,
+        event_queue,
+        event_sender,
+        status_icons,
+        persist_badge,
+        platform_specific,
+    } = tray_builder;
+
+    let thread_executor = event_loop.create_thread_executor();
+    debug_assert!(
+        thread_executor.in_event_loop_thread(),
+        "Trays must be built on the thread running their event loop"
+    );
+    if !thread_executor.in_event_loop_thread() {
+        return Err(TrayError::WrongThread);
+    }
+
+    let base_icon = match (platform_specific.icon_path, custom_icon) {
+        (Some(path), _) => {
+            // The icon isn't added yet, so this is the size for the primary taskbar.
+            let size = recommended_icon_size(0, 0);
+            let icon = load_icon_file(&path, size).map_err(TrayError::InvalidIcon)?;
+            BaseIcon::File { path, size, icon }
+        }
+        (None, Some(icon)) => BaseIcon::Custom(icon),
+        (None, None) => BaseIcon::Shared(load_default_icon(require_icon)?),
+    };
+    let state = Arc::new(Mutex::new(TrayState::new(base_icon, status_icons)));
+
     let hmodule = unsafe { GetModuleHandleW(std::ptr::null()) };
     if hmodule == 0 {
-        return Err(os_error!(std::io::Error::last_os_error()));
+        return Err(os_error!(std::io::Error::last_os_error()).into());
     }
 
-    let class_name = util::encode_wide("my_window");
+    let class_name = util::encode_wide(WINDOW_CLASS);
 
     let wnd = WNDCLASSEXW {
         cbSize: std::mem::size_of::<WNDCLASSEXW>() as u32,
@@ -196,26 +1388,107 @@ pub fn init_window<T: 'static>(
 
     let parent_hwnd = match parent_window {
         Some(RawWindowHandle::Win32(handle)) => Some(handle.hwnd.get()),
-        Some(_) => unreachable!("Invalid raw window handle {parent_window:?} on Windows"),
+        Some(_) => return Err(TrayError::InvalidParentWindow),
         _ => None,
     };
 
+    #[cfg(feature = "tray-menu")]
+    let menu = {
+        let menu = match platform_specific.raw_menu {
+            Some((hmenu, owned)) => Some(MenuState::from_raw(hmenu, owned)),
+            None => menu.map(MenuState::new),
+        };
+        let menu = menu.transpose().map_err(|err| os_error!(err))?;
+        if let Some(id) = menu.as_ref().and_then(MenuState::reserved_item_id) {
+            return Err(TrayError::InvalidMenuItemId(id));
+        }
+        if let Some(id) = primary_item {
+            if !menu.as_ref().map_or(false, |menu| menu.contains_item(id)) {
+                return Err(TrayError::UnknownMenuItem(id));
+            }
+        }
+        menu
+    };
+    // Message-only windows receive no broadcasts, so they are only used when asked for.
+    let window_kind = window_kind.unwrap_or(match parent_hwnd {
+        Some(_) => WindowKind::Owned,
+        None => WindowKind::TopLevel,
+    });
+    let (style, owner) = match window_kind {
+        WindowKind::MessageOnly => (0, HWND_MESSAGE),
+        WindowKind::Owned => (WS_OVERLAPPEDWINDOW, parent_hwnd.unwrap_or(0) as HWND),
+        WindowKind::TopLevel => (WS_OVERLAPPEDWINDOW, 0),
+    };
+
+    let uid = next_uid();
+    if let Some(name) = persist_badge {
+        if let Err(err) = persist::prune() {
+            warn!("Failed to prune the saved tray statuses: {err}");
+        }
+        // Shown right away, so the icon doesn't flash its base version first.
+        let status = persist::load_status(&name).unwrap_or_else(|err| {
+            warn!("Failed to load the tray status: {err}");
+            None
+        });
+        let restored = state
+            .lock()
+            .unwrap()
+            .restore_status(name.clone(), status.as_deref());
+        // The status was removed from the set since it was saved.
+        if status.is_some() && restored.is_none() {
+            if let Err(err) = persist::save_status(&name, None) {
+                warn!("Failed to forget the tray status: {err}");
+            }
+        }
+    }
+
+    let has_hover_flyout = hover_flyout.is_some();
+    let callback_message = platform_specific
+        .callback_message
+        .unwrap_or(DEFAULT_CALLBACK_MESSAGE);
     let mut initdata = InitData {
         event_loop,
+        tray_id: TrayId(uid),
+        uid,
+        tray_state: state.clone(),
+        #[cfg(feature = "tray-menu")]
+        menu,
+        primary_action,
+        #[cfg(feature = "tray-menu")]
+        primary_item,
+        tooltip_fn,
+        #[cfg(feature = "tray-menu")]
+        menu_trigger,
+        hover_flyout: hover_flyout.map(|(delay, builder)| HoverFlyout {
+            delay,
+            builder,
+            window: RefCell::new(None),
+        }),
+        shell_retries,
+        emit_cursor_moved,
+        event_queue,
+        event_sender,
+        callback_message,
+        adopted: false,
         window: None,
     };
 
+    // Resolved before the window is created, so that failing doesn't leave it behind.
+    // The tooltip is checked by `Tray::validate`.
+    let wide_tooltip = tooltip.as_deref().map(encode_tooltip).transpose()?;
+    let hicon = state.lock().unwrap().displayed_icon().map_err(TrayError::InvalidIcon)?;
+
     let hwnd = unsafe {
         CreateWindowExW(
             0,
             class_name.as_ptr(),
-            util::encode_wide(tooltip.unwrap_or("rust_systray_window".to_string())).as_ptr(),
-            WS_OVERLAPPEDWINDOW,
+            util::encode_wide(tooltip.as_deref().unwrap_or("rust_systray_window")).as_ptr(),
+            style,
             CW_USEDEFAULT,
             0,
             CW_USEDEFAULT,
             0,
-            parent_hwnd.unwrap_or(0) as HWND,
+            owner,
             0,
             util::get_instance_handle(),
             &mut initdata as *mut _ as *mut _,
@@ -228,53 +1501,187 @@ pub fn init_window<T: 'static>(
     }
 
     if hwnd == 0 {
-        return Err(os_error!(std::io::Error::last_os_error()));
+        return Err(os_error!(std::io::Error::last_os_error()).into());
     }
 
     // If the handle is non-null, then window creation must have succeeded, which means
     // that we *must* have populated the `InitData.window` field.
     // let win = initdata.window.unwrap();
 
-    let icon: HICON = unsafe {
-        let mut handle = LoadIconW(
-            GetModuleHandleW(std::ptr::null()),
-            util::encode_wide("tray-default").as_ptr(),
-        );
-        if handle == 0 {
-            handle = LoadIconW(0, IDI_APPLICATION);
-        }
-        if handle == 0 {
-            return Err(os_error!(std::io::Error::last_os_error()));
-        }
-        handle as HICON
-    };
-
     let mut nid = unsafe { std::mem::zeroed::<NOTIFYICONDATAW>() };
     nid.cbSize = std::mem::size_of::<NOTIFYICONDATAW>() as u32;
     nid.hWnd = hwnd;
-    nid.uID = 1;
+    nid.uID = uid;
     nid.uFlags = NIF_MESSAGE | NIF_ICON;
-    nid.hIcon = icon;
-    nid.uCallbackMessage = WM_USER + 1;
+    if !has_hover_flyout {
+        nid.uFlags |= NIF_SHOWTIP;
+    }
+    if let Some(wide_tooltip) = &wide_tooltip {
+        nid.uFlags |= NIF_TIP;
+        nid.szTip = tip_buffer(wide_tooltip);
+    }
+    nid.hIcon = hicon;
+    nid.uCallbackMessage = callback_message;
+    if prefer_promoted || start_hidden {
+        nid.uFlags |= NIF_STATE;
+        nid.dwState = if start_hidden { NIS_HIDDEN } else { 0 };
+        nid.dwStateMask = NIS_HIDDEN;
+    }
 
-    if unsafe { Shell_NotifyIconW(NIM_ADD, &nid) } == 0 {
-        return Err(os_error!(std::io::Error::last_os_error()));
+    if let Err(err) = shell_notify_retry(NIM_ADD, &nid, shell_retries) {
+        unsafe { DestroyWindow(hwnd) };
+        return Err(TrayError::ShellCallFailed(os_error!(err)));
     }
+    LIVE_TRAYS.lock().unwrap().push((TrayId(uid), hwnd, uid));
+    state.lock().unwrap().icon_rect = physical_icon_rect(hwnd, uid);
+    // Version 4 reports keyboard activation and the context menu key, which the legacy behavior
+    // translates to mouse clicks. Older shells only support the latter, which the callback then
+    // decodes instead.
+    nid.Anonymous.uVersion = NOTIFYICON_VERSION_4;
+    let shell_version = if unsafe { Shell_NotifyIconW(NIM_SETVERSION, &nid) } != 0 {
+        NOTIFYICON_VERSION_4
+    } else {
+        let err = std::io::Error::last_os_error();
+        warn!("Failed to set the tray notification version, using the legacy one: {err}");
+        0
+    };
+    debug!("Negotiated tray notification version {shell_version}");
+    let userdata = unsafe { super::get_window_long(hwnd, GWL_USERDATA) } as *const WindowData<T>;
+    unsafe { (*userdata).shell_version.set(shell_version) };
+    if let Err(err) = orphans::record(hwnd, uid, WINDOW_CLASS) {
+        warn!("Failed to record the tray icon for cleanup: {err}");
+    }
+    if prefer_promoted {
+        if let Err(err) = promotion::promote_current_exe() {
+            warn!("Failed to promote the tray icon: {err}");
+        }
+    }
+    unsafe { (*userdata).update_visibility() };
+    promotion::watch_settings(post_settings_changed);
 
-    // Setup menu
-    let mut info = unsafe { std::mem::zeroed::<MENUINFO>() };
-    info.cbSize = std::mem::size_of::<MENUINFO>() as u32;
-    info.fMask = MIM_APPLYTOSUBMENUS | MIM_STYLE;
-    info.dwStyle = MNS_NOTIFYBYPOS;
-    let hmenu = unsafe { CreatePopupMenu() };
-    if hmenu == 0 {
-        return Err(os_error!(std::io::Error::last_os_error()));
+    Ok(Tray {
+        window: hwnd,
+        id: TrayId(uid),
+        uid,
+        thread_id: unsafe { GetCurrentThreadId() },
+        state,
+        has_hover_flyout,
+        parent_window: parent_hwnd,
+        shell_retries,
+        adopted: false,
+        #[cfg(feature = "tray-toast")]
+        app_user_model_id: platform_specific.app_user_model_id.map(Into::into),
+    })
+}
+
+/// Subclasses `window` to handle the notifications of its icon `uid`, added by other code, and
+/// redirects them to a message of ours.
+///
+/// # Safety
+///
+/// See `Tray::from_raw`.
+unsafe fn adopt_icon<T: 'static>(
+    event_loop: &EventLoopWindowTarget<T>,
+    window: HWND,
+    uid: u32,
+    guid: Option<u128>,
+) -> Result<Tray, TrayError> {
+    if !event_loop.create_thread_executor().in_event_loop_thread() {
+        return Err(TrayError::WrongThread);
     }
-    if unsafe { SetMenuInfo(hmenu, &info) } == 0 {
-        return Err(os_error!(std::io::Error::last_os_error()));
+    // Icons are only identified by their window and `uID`.
+    if guid.is_some() {
+        let err = std::io::Error::new(std::io::ErrorKind::Unsupported, "tray icon GUIDs");
+        return Err(os_error!(err).into());
     }
+    let id = match adopted_tray_id(window, uid) {
+        Some(id) => id,
+        None => {
+            let err = std::io::Error::from(std::io::ErrorKind::AlreadyExists);
+            return Err(os_error!(err).into());
+        }
+    };
 
-    Ok(Tray(hwnd))
+    // The icon of the other code isn't known until another one is set.
+    let base_icon = BaseIcon::Shared(load_default_icon(false)?);
+    let state = Arc::new(Mutex::new(TrayState::new(base_icon, None)));
+    let defaults = TrayBuilder::new();
+    let shell_retries = defaults.shell_retries;
+    let callback_message = ADOPTED_CALLBACK_MSG_ID.get();
+    let mut initdata = InitData {
+        event_loop,
+        tray_id: id,
+        uid,
+        tray_state: state.clone(),
+        #[cfg(feature = "tray-menu")]
+        menu: None,
+        primary_action: None,
+        #[cfg(feature = "tray-menu")]
+        primary_item: None,
+        tooltip_fn: None,
+        #[cfg(feature = "tray-menu")]
+        menu_trigger: defaults.menu_trigger,
+        hover_flyout: None,
+        shell_retries,
+        emit_cursor_moved: false,
+        event_queue: None,
+        event_sender: None,
+        callback_message,
+        adopted: true,
+        window: None,
+    };
+    let userdata = match unsafe { initdata.on_nccreate(window) } {
+        Some(userdata) => userdata as *mut WindowData<T>,
+        None => {
+            let err = std::io::Error::new(std::io::ErrorKind::Other, "the tray setup panicked");
+            return Err(os_error!(err).into());
+        }
+    };
+    let subclassed = unsafe {
+        SetWindowSubclass(
+            window,
+            Some(subclass_proc::<T>),
+            ADOPTED_SUBCLASS_ID,
+            userdata as usize,
+        )
+    };
+    if subclassed == false.into() {
+        let err = std::io::Error::last_os_error();
+        drop(unsafe { Box::from_raw(userdata) });
+        return Err(os_error!(err).into());
+    }
+
+    let mut nid = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+    nid.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    nid.hWnd = window;
+    nid.uID = uid;
+    nid.uFlags = NIF_MESSAGE;
+    nid.uCallbackMessage = callback_message;
+    if let Err(err) = shell_notify_retry(NIM_MODIFY, &nid, shell_retries) {
+        unsafe { RemoveWindowSubclass(window, Some(subclass_proc::<T>), ADOPTED_SUBCLASS_ID) };
+        drop(unsafe { Box::from_raw(userdata) });
+        return Err(TrayError::ShellCallFailed(os_error!(err)));
+    }
+    nid.Anonymous.uVersion = NOTIFYICON_VERSION_4;
+    if unsafe { Shell_NotifyIconW(NIM_SETVERSION, &nid) } != 0 {
+        unsafe { (*userdata).shell_version.set(NOTIFYICON_VERSION_4) };
+    }
+
+    LIVE_TRAYS.lock().unwrap().push((id, window, uid));
+    state.lock().unwrap().icon_rect = physical_icon_rect(window, uid);
+    Ok(Tray {
+        window,
+        id,
+        uid,
+        thread_id: unsafe { GetCurrentThreadId() },
+        state,
+        has_hover_flyout: false,
+        parent_window: None,
+        shell_retries,
+        adopted: true,
+        #[cfg(feature = "tray-toast")]
+        app_user_model_id: None,
+    })
 }
 
 pub(crate) extern "system" fn window_proc<T: 'static>(
@@ -313,26 +1720,58 @@ pub(crate) extern "system" fn window_proc<T: 'static>(
         _ => userdata as *mut WindowData<T>,
     };
 
-    let (result, userdata_removed, recurse_depth) = {
-        let userdata = unsafe { &*(userdata_ptr) };
+    let (result, freed) = unsafe { dispatch_message(window, msg, w_param, l_param, userdata_ptr) };
+    if freed {
+        drop(unsafe { Box::from_raw(userdata_ptr) });
+    }
+    result
+}
 
-        userdata.recurse_depth.set(userdata.recurse_depth.get() + 1);
+/// The subclass procedure of the windows of adopted icons, whose userdata is `ref_data`.
+extern "system" fn subclass_proc<T: 'static>(
+    window: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+    _subclass_id: usize,
+    ref_data: usize,
+) -> LRESULT {
+    let userdata_ptr = ref_data as *mut WindowData<T>;
+    // Our messages are all registered ones, and those that aren't ours are passed on by the
+    // callback.
+    let ours = msg >= 0xC000 || msg == WM_DESTROY || msg == WM_NCDESTROY;
+    if !ours {
+        return unsafe { DefSubclassProc(window, msg, w_param, l_param) };
+    }
 
-        let result =
-            unsafe { public_window_callback_inner(window, msg, w_param, l_param, userdata) };
+    let (result, freed) = unsafe { dispatch_message(window, msg, w_param, l_param, userdata_ptr) };
+    if freed {
+        unsafe { RemoveWindowSubclass(window, Some(subclass_proc::<T>), ADOPTED_SUBCLASS_ID) };
+        drop(unsafe { Box::from_raw(userdata_ptr) });
+    }
+    result
+}
 
-        let userdata_removed = userdata.userdata_removed.get();
-        let recurse_depth = userdata.recurse_depth.get() - 1;
-        userdata.recurse_depth.set(recurse_depth);
+/// Handles `msg` with the userdata at `userdata_ptr`, returning whether the userdata was removed
+/// and can be freed.
+unsafe fn dispatch_message<T: 'static>(
+    window: HWND,
+    msg: u32,
+    w_param: WPARAM,
+    l_param: LPARAM,
+    userdata_ptr: *mut WindowData<T>,
+) -> (LRESULT, bool) {
+    let userdata = unsafe { &*(userdata_ptr) };
 
-        (result, userdata_removed, recurse_depth)
-    };
+    userdata.recurse_depth.set(userdata.recurse_depth.get() + 1);
 
-    if userdata_removed && recurse_depth == 0 {
-        drop(unsafe { Box::from_raw(userdata_ptr) });
-    }
+    let result = unsafe { public_window_callback_inner(window, msg, w_param, l_param, userdata) };
 
-    result
+    let userdata_removed = userdata.userdata_removed.get();
+    let recurse_depth = userdata.recurse_depth.get() - 1;
+    userdata.recurse_depth.set(recurse_depth);
+
+    (result, userdata_removed && recurse_depth == 0)
 }
 
 unsafe fn public_window_callback_inner<T: 'static>(
@@ -343,18 +1782,29 @@ unsafe fn public_window_callback_inner<T: 'static>(
     userdata: &WindowData<T>,
 ) -> LRESULT {
     let mut result = ProcResult::DefWindowProc(w_param);
+    // With `NOTIFYICON_VERSION_4`, the low word of LPARAM is the notification and WPARAM holds
+    // the coordinates it happened at. The legacy version uses all of LPARAM for the
+    // notification, and WPARAM for the icon identifier.
+    let legacy = userdata.shell_version.get() != NOTIFYICON_VERSION_4;
+    let notification = if legacy {
+        l_param as u32
+    } else {
+        super::loword(l_param as u32) as u32
+    };
+    let is_callback = msg == userdata.callback_message;
 
     match msg {
-        1025 if (l_param as u32 == WM_LBUTTONUP
-            || l_param as u32 == WM_RBUTTONUP
-            || l_param as u32 == WM_MBUTTONUP
-            || l_param as u32 == WM_XBUTTONUP
-            || l_param as u32 == WM_LBUTTONDOWN
-            || l_param as u32 == WM_RBUTTONDOWN
-            || l_param as u32 == WM_MBUTTONDOWN
-            || l_param as u32 == WM_XBUTTONDOWN) =>
+        _ if is_callback
+            && (notification == WM_LBUTTONUP
+                || notification == WM_RBUTTONUP
+                || notification == WM_MBUTTONUP
+                || notification == WM_XBUTTONUP
+                || notification == WM_LBUTTONDOWN
+                || notification == WM_RBUTTONDOWN
+                || notification == WM_MBUTTONDOWN
+                || notification == WM_XBUTTONDOWN) =>
         {
-            let (button, state) = match l_param as u32 {
+            let (button, state) = match notification {
                 x if x == WM_LBUTTONUP => (
                     crate::event::MouseButton::Left,
                     crate::event::ElementState::Released,
@@ -391,20 +1841,31 @@ unsafe fn public_window_callback_inner<T: 'static>(
             };
 
             use crate::event::WindowEvent::{CursorMoved, MouseInput};
-            let mut point = POINT { x: 0, y: 0 };
-            if unsafe { GetCursorPos(&mut point) } == 0 {
-                return 1;
-            }
-            let position = PhysicalPosition::new(point.x as f64, point.y as f64);
-
-            userdata.send_event(Event::WindowEvent {
-                window_id: RootWindowId(WindowId(window)),
-                event: CursorMoved {
-                    device_id: DEVICE_ID,
-                    position,
-                },
+            // Falls back to where the shell says the click happened, which the legacy version
+            // doesn't report.
+            let position = util::cursor_position().unwrap_or_else(|| {
+                if legacy {
+                    PhysicalPosition::default()
+                } else {
+                    PhysicalPosition::new(
+                        super::get_x_lparam(w_param as u32) as f64,
+                        super::get_y_lparam(w_param as u32) as f64,
+                    )
+                }
             });
 
+            let from_overflow = is_in_overflow(position.cast());
+
+            if userdata.emit_cursor_moved {
+                userdata.send_event(Event::WindowEvent {
+                    window_id: RootWindowId(WindowId(window)),
+                    event: CursorMoved {
+                        device_id: DEVICE_ID,
+                        position,
+                    },
+                });
+            }
+
             userdata.send_event(Event::WindowEvent {
                 window_id: RootWindowId(WindowId(window)),
                 event: MouseInput {
@@ -414,23 +1875,407 @@ unsafe fn public_window_callback_inner<T: 'static>(
                 },
             });
 
+            match notification {
+                #[cfg(feature = "tray-menu")]
+                WM_LBUTTONDOWN if userdata.menu_trigger == MenuTrigger::RightClickOrLongPress => {
+                    userdata.long_pressed.set(false);
+                    unsafe { SetTimer(window, LONG_PRESS_TIMER_ID, LONG_PRESS_DELAY_MS, None) };
+                }
+                WM_LBUTTONUP => {
+                    #[cfg(feature = "tray-menu")]
+                    unsafe { KillTimer(window, LONG_PRESS_TIMER_ID) };
+                    #[cfg(feature = "tray-menu")]
+                    let long_pressed = userdata.long_pressed.replace(false);
+                    #[cfg(not(feature = "tray-menu"))]
+                    let long_pressed = false;
+                    if !long_pressed {
+                        userdata.send_tray_event(TrayEvent::LeftClicked {
+                            position,
+                            from_overflow,
+                        });
+                        if let (Some(action), Some(runner)) = (
+                            &userdata.primary_action,
+                            userdata.event_loop_runner.upgrade(),
+                        ) {
+                            runner.catch_unwind(action);
+                        }
+                        #[cfg(feature = "tray-menu")]
+                        if let Some(id) = userdata.primary_item {
+                            let data = match userdata.menu.try_borrow().as_deref() {
+                                Ok(Some(menu)) => menu.item_data(id),
+                                _ => None,
+                            };
+                            userdata.send_tray_event(TrayEvent::MenuItemClicked { id, data });
+                        }
+                    }
+                }
+                WM_RBUTTONUP => {
+                    userdata.send_tray_event(TrayEvent::RightClicked {
+                        position,
+                        from_overflow,
+                    });
+                    #[cfg(feature = "tray-menu")]
+                    if legacy {
+                        // The legacy version sends no `WM_CONTEXTMENU`.
+                        userdata.show_menu(window, position.cast(), false);
+                    } else {
+                        // The menu is shown by the `WM_CONTEXTMENU` that follows.
+                        userdata.right_clicked.set(true);
+                    }
+                }
+                _ => (),
+            }
+
+            result = ProcResult::Value(0);
+        }
+
+        _ if is_callback && notification == WM_MOUSEMOVE => {
+            let stale = userdata
+                .tooltip_generated
+                .get()
+                .map_or(true, |generated| generated.elapsed() >= TOOLTIP_FN_INTERVAL);
+            if let (Some(tooltip_fn), Some(runner), true) = (
+                &userdata.tooltip_fn,
+                userdata.event_loop_runner.upgrade(),
+                stale,
+            ) {
+                userdata.tooltip_generated.set(Some(Instant::now()));
+                if let Some(tooltip) = runner.catch_unwind(tooltip_fn) {
+                    let wide_tooltip = encode_tooltip_truncated(&tooltip);
+                    let show_tip = userdata.hover_flyout.is_none();
+                    let uid = userdata.uid;
+                    let retries = userdata.shell_retries;
+                    if let Err(err) = modify_tooltip(window, uid, &wide_tooltip, show_tip, retries)
+                    {
+                        warn!("Failed to update the tray tooltip: {err}");
+                    }
+                }
+            }
+            result = ProcResult::Value(0);
+        }
+
+        WM_DPICHANGED => {
+            // Picks the frame of an icon file matching the new DPI.
+            let uid = userdata.uid;
+            let size = recommended_icon_size(window, uid);
+            let retries = userdata.shell_retries;
+            if let Err(err) = update_icon(window, uid, &userdata.tray_state, retries, |state| {
+                state.reload_icon_file(size)
+            }) {
+                warn!("Failed to reload the tray icon: {err}");
+            }
+            result = ProcResult::Value(0);
+        }
+
+        WM_DISPLAYCHANGE => {
+            let uid = userdata.uid;
+            let size = recommended_icon_size(window, uid);
+            let retries = userdata.shell_retries;
+            if let Err(err) = update_icon(window, uid, &userdata.tray_state, retries, |state| {
+                state.reload_icon_file(size)
+            }) {
+                warn!("Failed to reload the tray icon: {err}");
+            }
+            // Reported here rather than as a move.
+            let rect = physical_icon_rect(window, uid);
+            userdata.tray_state.lock().unwrap().icon_rect = rect;
+            userdata.send_tray_event(TrayEvent::DisplayChanged(rect));
+            result = ProcResult::DefWindowProc(w_param);
+        }
+
+        #[cfg(feature = "tray-menu")]
+        WM_TIMER if w_param == LONG_PRESS_TIMER_ID => {
+            unsafe { KillTimer(window, LONG_PRESS_TIMER_ID) };
+            userdata.long_pressed.set(true);
+            if let Some(position) = util::cursor_position() {
+                userdata.show_menu(window, position.cast(), false);
+            }
+            result = ProcResult::Value(0);
+        }
+
+        _ if is_callback && notification == NIN_POPUPOPEN => {
+            if let Some(flyout) = &userdata.hover_flyout {
+                let delay = flyout.delay.as_millis().min(u32::MAX as u128) as u32;
+                unsafe { SetTimer(window, HOVER_TIMER_ID, delay, None) };
+            }
+            result = ProcResult::Value(0);
+        }
+
+        _ if is_callback && notification == NIN_POPUPCLOSE => {
+            // Cancels the flyout if the cursor left before the delay elapsed.
+            unsafe { KillTimer(window, HOVER_TIMER_ID) };
+            if let Some(flyout) = &userdata.hover_flyout {
+                flyout.hide();
+            }
+            result = ProcResult::Value(0);
+        }
+
+        WM_SETTINGCHANGE => {
+            // Moving, resizing or hiding the taskbar changes the work area, among other settings.
+            unsafe { SetTimer(window, ICON_MOVED_TIMER_ID, ICON_MOVED_DELAY_MS, None) };
+            result = ProcResult::DefWindowProc(w_param);
+        }
+
+        WM_TIMER if w_param == ICON_MOVED_TIMER_ID => {
+            unsafe { KillTimer(window, ICON_MOVED_TIMER_ID) };
+            let rect = physical_icon_rect(window, userdata.uid);
+            let previous = mem::replace(&mut userdata.tray_state.lock().unwrap().icon_rect, rect);
+            match rect {
+                Some(rect) if previous != Some(rect) => {
+                    userdata.send_tray_event(TrayEvent::IconMoved(rect))
+                }
+                _ => (),
+            }
+            userdata.update_visibility();
+            result = ProcResult::Value(0);
+        }
+
+        WM_TIMER if w_param == HOVER_TIMER_ID => {
+            unsafe { KillTimer(window, HOVER_TIMER_ID) };
+            if let (Some(flyout), Some(runner)) =
+                (&userdata.hover_flyout, userdata.event_loop_runner.upgrade())
+            {
+                flyout.show(window, userdata.uid, runner);
+            }
+            result = ProcResult::Value(0);
+        }
+
+        #[cfg(feature = "tray-menu")]
+        _ if is_callback && (notification == WM_CONTEXTMENU || notification == NIN_KEYSELECT) => {
+            // Space and enter select the icon, while the menu key and Shift+F10 send a
+            // `WM_CONTEXTMENU` that isn't preceded by a right click.
+            let from_keyboard =
+                notification == NIN_KEYSELECT || !userdata.right_clicked.replace(false);
+            let anchor = PhysicalPosition::new(
+                super::get_x_lparam(w_param as u32) as i32,
+                super::get_y_lparam(w_param as u32) as i32,
+            );
+            let position = match icon_rect(window, userdata.uid) {
+                Some(rect) if from_keyboard => {
+                    PhysicalPosition::new((rect.left + rect.right) / 2, rect.top)
+                }
+                _ => anchor,
+            };
+            userdata.show_menu(window, position, from_keyboard);
+            result = ProcResult::Value(0);
+        }
+
+        #[cfg(feature = "tray-notifications")]
+        _ if is_callback && notification == NIN_BALLOONSHOW => {
+            userdata
+                .balloon_callback
+                .set(userdata.next_balloon_callback.take());
+            result = ProcResult::Value(0);
+        }
+
+        #[cfg(feature = "tray-notifications")]
+        _ if is_callback && notification == NIN_BALLOONUSERCLICK => {
+            let on_click = userdata.balloon_callback.take();
+            if let (Some(on_click), Some(runner)) = (on_click, userdata.event_loop_runner.upgrade())
+            {
+                runner.catch_unwind(on_click);
+            }
+            result = ProcResult::Value(0);
+        }
+
+        // The callback is dropped without being called when the balloon is dismissed.
+        #[cfg(feature = "tray-notifications")]
+        _ if is_callback
+            && (notification == NIN_BALLOONHIDE || notification == NIN_BALLOONTIMEOUT) =>
+        {
+            drop(userdata.balloon_callback.take());
+            result = ProcResult::Value(0);
+        }
+
+        #[cfg(feature = "tray-menu")]
+        WM_INITMENUPOPUP => {
+            if let Some(menu) = userdata.menu.borrow_mut().as_mut() {
+                if let Some(runner) = userdata.event_loop_runner.upgrade() {
+                    runner.catch_unwind(|| menu.populate_lazy_menu(w_param as HMENU));
+                }
+            }
+            result = ProcResult::Value(0);
+        }
+
+        WM_DESTROY => {
+            // Everything is released here so that it happens however the window is destroyed.
+            userdata.release(window);
+            result = if userdata.adopted {
+                ProcResult::DefWindowProc(w_param)
+            } else {
+                ProcResult::Value(0)
+            };
+        }
+
+        WM_NCDESTROY => {
+            userdata.userdata_removed.set(true);
+            result = if userdata.adopted {
+                ProcResult::DefWindowProc(w_param)
+            } else {
+                unsafe { super::set_window_long(window, GWL_USERDATA, 0) };
+                ProcResult::Value(0)
+            };
+        }
+
+        #[cfg(feature = "tray-menu")]
+        WM_MENUCOMMAND => {
+            let id = unsafe { GetMenuItemID(l_param as HMENU, w_param as i32) };
+            // Items opening a submenu don't have an identifier.
+            if id != u32::MAX {
+                let id = MenuId(id);
+                let data = match userdata.menu.try_borrow().as_deref() {
+                    Ok(Some(menu)) => menu.item_data(id),
+                    _ => None,
+                };
+                userdata.send_tray_event(TrayEvent::MenuItemClicked { id, data });
+            }
             result = ProcResult::Value(0);
         }
 
         _ => {
-            if msg == DESTROY_MSG_ID.get() {
-                unsafe { DestroyWindow(window) };
+            if msg == TRAY_DESTROY_MSG_ID.get() {
+                if userdata.adopted {
+                    // The window isn't ours, so only the icon and subclass are removed.
+                    userdata.release(window);
+                    userdata.userdata_removed.set(true);
+                } else {
+                    unsafe { DestroyWindow(window) };
+                }
+                result = ProcResult::Value(0);
+            } else if msg == IS_PUMP_ALIVE_MSG_ID.get() {
+                let pumping = userdata
+                    .event_loop_runner
+                    .upgrade()
+                    .map_or(false, |runner| runner.is_pumping());
+                result = ProcResult::Value(pumping.into());
+            } else if msg == SET_ICON_MSG_ID.get() {
+                let icon = unsafe { Box::from_raw(l_param as *mut Icon) };
+                let uid = userdata.uid;
+                let retries = userdata.shell_retries;
+                if let Err(err) = update_icon(window, uid, &userdata.tray_state, retries, |state| {
+                    state.set_icon(*icon)
+                }) {
+                    warn!("Failed to update the tray icon: {err}");
+                }
+                result = ProcResult::Value(0);
+            } else if msg == SET_TOOLTIP_MSG_ID.get() {
+                let wide_tooltip = unsafe { Box::from_raw(l_param as *mut Vec<u16>) };
+                let show_tip = userdata.hover_flyout.is_none();
+                let uid = userdata.uid;
+                let retries = userdata.shell_retries;
+                if let Err(err) = modify_tooltip(window, uid, &wide_tooltip, show_tip, retries) {
+                    warn!("Failed to update the tray tooltip: {err}");
+                }
                 result = ProcResult::Value(0);
             } else {
+                #[cfg(feature = "tray-menu")]
+                if msg == MENU_SIZE_MSG_ID.get() {
+                    let size = unsafe { &mut *(l_param as *mut Option<PhysicalSize<u32>>) };
+                    if let Ok(Some(menu)) = userdata.menu.try_borrow().as_deref() {
+                        match menu.size() {
+                            Ok(menu_size) => *size = Some(menu_size),
+                            Err(err) => warn!("Failed to measure the tray menu: {err}"),
+                        }
+                    }
+                    return 0;
+                } else if msg == SET_MENU_ENABLED_MSG_ID.get() {
+                    userdata.menu_enabled.set(w_param != 0);
+                    return 0;
+                } else if msg == SET_ITEM_LABEL_MSG_ID.get() {
+                    let id = MenuId(w_param as u32);
+                    let label = unsafe { &*(l_param as *const String) };
+                    return match userdata.menu.try_borrow_mut().as_deref_mut() {
+                        Ok(Some(menu)) if menu.contains_item(id) => {
+                            match menu.set_item_label(id, label.clone()) {
+                                Ok(()) => 0,
+                                Err(err) => err
+                                    .raw_os_error()
+                                    .unwrap_or(ERROR_INVALID_PARAMETER as i32)
+                                    as LRESULT,
+                            }
+                        }
+                        Ok(_) => UNKNOWN_MENU_ITEM,
+                        // The menu is being populated.
+                        Err(_) => ERROR_BUSY as LRESULT,
+                    };
+                }
+                #[cfg(feature = "tray-notifications")]
+                if msg == SWAP_BALLOON_CALLBACK_MSG_ID.get() {
+                    let on_click = unsafe { &mut *(l_param as *mut Option<ClickCallback>) };
+                    let next = userdata.next_balloon_callback.take();
+                    userdata.next_balloon_callback.set(mem::replace(on_click, next));
+                    return 0;
+                }
+                #[cfg(feature = "tray-toast")]
+                if msg == TOAST_ACTION_MSG_ID.get() {
+                    let event = unsafe { Box::from_raw(l_param as *mut TrayEvent) };
+                    userdata.send_tray_event(*event);
+                    return 0;
+                }
+                #[cfg(all(feature = "tray-mock", debug_assertions))]
+                if msg == INJECT_EVENT_MSG_ID.get() {
+                    let event = unsafe { Box::from_raw(l_param as *mut TrayEvent) };
+                    userdata.send_tray_event(*event);
+                    return 0;
+                }
+                if msg == SETTINGS_CHANGED_MSG_ID.get() {
+                    userdata.update_visibility();
+                }
+
                 result = ProcResult::DefWindowProc(w_param);
             }
         }
     };
 
     match result {
+        ProcResult::DefWindowProc(wparam) if userdata.adopted => unsafe {
+            DefSubclassProc(window, msg, wparam, l_param)
+        },
         ProcResult::DefWindowProc(wparam) => unsafe {
             DefWindowProcW(window, msg, wparam, l_param)
         },
         ProcResult::Value(val) => val,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shorter_tooltip_clears_previous_one() {
+        let mut tip = tip_buffer(&encode_tooltip("A much longer tooltip").unwrap());
+        assert_eq!(tip[20], 'p' as u16);
+
+        tip = tip_buffer(&encode_tooltip("Short").unwrap());
+        let expected: Vec<u16> = "Short".encode_utf16().collect();
+        assert_eq!(tip[..5], expected[..]);
+        assert!(tip[5..].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn icon_file_satisfies_required_icon() {
+        use crate::platform::windows::TrayBuilderExtWindows;
+
+        // The test executable has no default tray icon resource.
+        let builder = TrayBuilder::new()
+            .require_icon(true)
+            .with_icon_from_path("icon.ico");
+        assert!(Tray::validate(&builder).is_empty());
+    }
+
+    #[test]
+    fn adopted_icon_keeps_next_uid_free() {
+        // The other code used the `uID` the next tray built would get.
+        let uid = next_uid() + 1;
+        let adopted = adopted_tray_id(1 as HWND, uid).unwrap();
+        assert_ne!(adopted, TrayId(next_uid()));
+    }
+
+    #[test]
+    fn tooltip_is_null_terminated() {
+        let tip = tip_buffer(&[u16::from(b'a'); 200]);
+        assert_eq!(tip[126], u16::from(b'a'));
+        assert_eq!(tip[127], 0);
+    }
+}
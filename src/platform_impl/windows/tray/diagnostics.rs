@@ -0,0 +1,105 @@
+use std::{io, mem, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{ERROR_SUCCESS, HWND},
+    System::Registry::{
+        RegGetValueW, HKEY, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD,
+    },
+    UI::{
+        Shell::{Shell_NotifyIconW, NIF_STATE, NIM_ADD, NIM_DELETE, NIS_HIDDEN, NOTIFYICONDATAW},
+        WindowsAndMessaging::{
+            CreateWindowExW, DestroyWindow, FindWindowExW, FindWindowW, HWND_MESSAGE,
+        },
+    },
+};
+
+use crate::{platform_impl::platform::util, tray::TrayDiagnostics};
+
+pub fn diagnose() -> TrayDiagnostics {
+    TrayDiagnostics {
+        notification_area_found: notification_area_found(),
+        hidden_by_policy: hidden_by_policy(),
+        test_icon_error: test_icon().err().and_then(|err| err.raw_os_error()),
+    }
+}
+
+fn notification_area_found() -> bool {
+    let taskbar_class = util::encode_wide("Shell_TrayWnd");
+    let notification_area_class = util::encode_wide("TrayNotifyWnd");
+    unsafe {
+        let taskbar = FindWindowW(taskbar_class.as_ptr(), ptr::null());
+        taskbar != 0
+            && FindWindowExW(taskbar, 0, notification_area_class.as_ptr(), ptr::null()) != 0
+    }
+}
+
+/// Whether the `NoTrayItemsDisplay` group policy hides the notification area.
+fn hidden_by_policy() -> bool {
+    let subkey =
+        util::encode_wide("Software\\Microsoft\\Windows\\CurrentVersion\\Policies\\Explorer");
+    let value = util::encode_wide("NoTrayItemsDisplay");
+
+    let is_set = |hkey: HKEY| {
+        let mut data = 0u32;
+        let mut size = mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            RegGetValueW(
+                hkey,
+                subkey.as_ptr(),
+                value.as_ptr(),
+                RRF_RT_REG_DWORD,
+                ptr::null_mut(),
+                &mut data as *mut u32 as *mut _,
+                &mut size,
+            )
+        };
+        status == ERROR_SUCCESS && data != 0
+    };
+
+    is_set(HKEY_LOCAL_MACHINE) || is_set(HKEY_CURRENT_USER)
+}
+
+/// Adds then removes a hidden icon on a temporary message-only window.
+fn test_icon() -> Result<(), io::Error> {
+    let class_name = util::encode_wide("STATIC");
+    let window = unsafe {
+        CreateWindowExW(
+            0,
+            class_name.as_ptr(),
+            ptr::null(),
+            0,
+            0,
+            0,
+            0,
+            0,
+            HWND_MESSAGE,
+            0,
+            util::get_instance_handle(),
+            ptr::null(),
+        )
+    };
+    if window == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let result = add_and_remove_icon(window);
+    unsafe { DestroyWindow(window) };
+    result
+}
+
+fn add_and_remove_icon(window: HWND) -> Result<(), io::Error> {
+    let mut nid = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+    nid.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    nid.hWnd = window;
+    nid.uFlags = NIF_STATE;
+    nid.dwState = NIS_HIDDEN;
+    nid.dwStateMask = NIS_HIDDEN;
+
+    if unsafe { Shell_NotifyIconW(NIM_ADD, &nid) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { Shell_NotifyIconW(NIM_DELETE, &nid) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use windows_sys::Win32::Foundation::RECT;
+
+use crate::dpi::{PhysicalPosition, PhysicalSize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TaskbarEdge {
+    Left,
+    Top,
+    Right,
+    Bottom,
+}
+
+/// Returns the edge of `monitor` the taskbar hosting `icon` is docked to.
+fn taskbar_edge(icon: &RECT, monitor: &RECT, work_area: &RECT) -> TaskbarEdge {
+    if work_area.bottom < monitor.bottom {
+        TaskbarEdge::Bottom
+    } else if work_area.top > monitor.top {
+        TaskbarEdge::Top
+    } else if work_area.left > monitor.left {
+        TaskbarEdge::Left
+    } else if work_area.right < monitor.right {
+        TaskbarEdge::Right
+    } else {
+        // An auto-hidden taskbar doesn't reserve any space, so pick the edge nearest to the icon.
+        [
+            (icon.top - monitor.top, TaskbarEdge::Top),
+            (monitor.bottom - icon.bottom, TaskbarEdge::Bottom),
+            (icon.left - monitor.left, TaskbarEdge::Left),
+            (monitor.right - icon.right, TaskbarEdge::Right),
+        ]
+        .into_iter()
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, edge)| edge)
+        .unwrap()
+    }
+}
+
+/// Returns the position of a window of `size` placed next to `icon`, against the taskbar and
+/// centered on the icon, without leaving the work area.
+pub fn flyout_position(
+    icon: &RECT,
+    monitor: &RECT,
+    work_area: &RECT,
+    size: PhysicalSize<u32>,
+) -> PhysicalPosition<i32> {
+    let width = size.width as i32;
+    let height = size.height as i32;
+    let centered_x = (icon.left + icon.right) / 2 - width / 2;
+    let centered_y = (icon.top + icon.bottom) / 2 - height / 2;
+
+    let (x, y) = match taskbar_edge(icon, monitor, work_area) {
+        TaskbarEdge::Bottom => (centered_x, work_area.bottom - height),
+        TaskbarEdge::Top => (centered_x, work_area.top),
+        TaskbarEdge::Left => (work_area.left, centered_y),
+        TaskbarEdge::Right => (work_area.right - width, centered_y),
+    };
+
+    // Icons close to a corner would otherwise push the window past the screen edge.
+    PhysicalPosition::new(
+        x.min(work_area.right - width).max(work_area.left),
+        y.min(work_area.bottom - height).max(work_area.top),
+    )
+}
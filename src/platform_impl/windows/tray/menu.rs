@@ -0,0 +1,432 @@
+use std::{collections::HashMap, io, mem, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{HWND, SIZE, WPARAM},
+    Graphics::Gdi::{
+        CreateFontIndirectW, DeleteObject, GetDC, GetTextExtentPoint32W, ReleaseDC, SelectObject,
+    },
+    UI::{
+        Input::KeyboardAndMouse::VK_DOWN,
+        WindowsAndMessaging::{
+            AppendMenuW, CreatePopupMenu, DeleteMenu, DestroyMenu, GetMenuInfo, GetMenuItemCount,
+            GetMenuItemID, GetMenuItemInfoW, GetMenuStringW, GetSubMenu, GetSystemMetrics,
+            PostMessageW, SetForegroundWindow, SetMenuInfo, SetMenuItemInfoW, SystemParametersInfoW,
+            TrackPopupMenuEx, HMENU, MENUINFO, MENUITEMINFOW, MFT_SEPARATOR, MF_BYPOSITION,
+            MF_GRAYED, MF_POPUP, MF_SEPARATOR, MF_STRING, MIIM_FTYPE, MIIM_ID, MIIM_STRING,
+            MIIM_SUBMENU, MIM_APPLYTOSUBMENUS, MIM_STYLE, MNS_NOTIFYBYPOS, NONCLIENTMETRICSW,
+            SM_CXEDGE, SM_CXMENUCHECK, SM_CYEDGE, SM_CYMENU, SM_MENUDROPALIGNMENT,
+            SPI_GETNONCLIENTMETRICS, TPM_BOTTOMALIGN, TPM_LEFTALIGN, TPM_RIGHTALIGN,
+            TPM_RIGHTBUTTON, TPM_VERPOSANIMATION, WM_KEYDOWN, WM_NULL,
+        },
+    },
+};
+
+use crate::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    platform_impl::platform::util,
+    tray::{MenuEntry, MenuId, MenuItemData, TrayMenu},
+};
+
+struct LazyMenu {
+    builder: Box<dyn Fn() -> TrayMenu>,
+    /// The lazy submenus created when the menu was last populated. They are destroyed along with
+    /// the items of the menu, so their builders must be forgotten when it is repopulated.
+    children: Vec<HMENU>,
+}
+
+struct MenuItem {
+    label: String,
+    data: Option<MenuItemData>,
+}
+
+/// The Win32 menu built from a `TrayMenu`.
+pub(crate) struct MenuState {
+    hmenu: HMENU,
+    lazy_menus: HashMap<HMENU, LazyMenu>,
+    /// The items currently in the menu, by identifier.
+    items: HashMap<MenuId, MenuItem>,
+    /// Whether the menu is destroyed along with the state, which isn't the case of raw menus
+    /// the application keeps ownership of.
+    owned: bool,
+}
+
+impl MenuState {
+    pub fn new(menu: TrayMenu) -> Result<MenuState, io::Error> {
+        let hmenu = create_popup_menu()?;
+        let mut lazy_menus = HashMap::new();
+        let mut items = HashMap::new();
+        if let Err(err) = append_entries(hmenu, menu, &mut lazy_menus, &mut items, &mut Vec::new())
+        {
+            unsafe { DestroyMenu(hmenu) };
+            return Err(err);
+        }
+
+        Ok(MenuState {
+            hmenu,
+            lazy_menus,
+            items,
+            owned: true,
+        })
+    }
+
+    /// Wraps the existing popup menu `hmenu`, destroying it when dropped if `owned` is set.
+    pub fn from_raw(hmenu: HMENU, owned: bool) -> Result<MenuState, io::Error> {
+        // Items must be reported through `WM_MENUCOMMAND` like those of built menus.
+        let mut info = unsafe { mem::zeroed::<MENUINFO>() };
+        info.cbSize = mem::size_of::<MENUINFO>() as u32;
+        info.fMask = MIM_STYLE;
+        if unsafe { GetMenuInfo(hmenu, &mut info) } == false.into() {
+            return Err(io::Error::last_os_error());
+        }
+        info.fMask = MIM_STYLE | MIM_APPLYTOSUBMENUS;
+        info.dwStyle |= MNS_NOTIFYBYPOS;
+        if unsafe { SetMenuInfo(hmenu, &info) } == false.into() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut items = HashMap::new();
+        read_items(hmenu, &mut items)?;
+        Ok(MenuState {
+            hmenu,
+            lazy_menus: HashMap::new(),
+            items,
+            owned,
+        })
+    }
+
+    pub fn hmenu(&self) -> HMENU {
+        self.hmenu
+    }
+
+    /// Estimates the size the menu is shown at, without its lazy submenus being populated.
+    pub fn size(&self) -> Result<PhysicalSize<u32>, io::Error> {
+        measure_menu(self.hmenu)
+    }
+
+    /// Returns the identifier of an item in the range reserved for the items of `TrayMenu`s, which
+    /// raw menus must not use as they would be mistaken for them.
+    pub fn reserved_item_id(&self) -> Option<u32> {
+        self.items
+            .keys()
+            .map(|id| id.0)
+            .find(|&id| id >= MenuId::FIRST)
+    }
+
+    pub fn contains_item(&self, id: MenuId) -> bool {
+        self.items.contains_key(&id)
+    }
+
+    pub fn item_data(&self, id: MenuId) -> Option<MenuItemData> {
+        self.items.get(&id).and_then(|item| item.data.clone())
+    }
+
+    /// Changes the label of the item `id`, which is passed as-is like when the item was created.
+    pub fn set_item_label(&mut self, id: MenuId, label: String) -> Result<(), io::Error> {
+        // Status items are typically refreshed periodically, often with the same text.
+        if self.items.get(&id).map_or(false, |item| item.label == label) {
+            return Ok(());
+        }
+
+        let wide_label = util::encode_wide(&label);
+        let mut info = unsafe { mem::zeroed::<MENUITEMINFOW>() };
+        info.cbSize = mem::size_of::<MENUITEMINFOW>() as u32;
+        info.fMask = MIIM_STRING;
+        info.dwTypeData = wide_label.as_ptr() as *mut u16;
+
+        // Looking up the item by identifier also searches the submenus.
+        if unsafe { SetMenuItemInfoW(self.hmenu, id.0, false.into(), &info) } == false.into() {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(item) = self.items.get_mut(&id) {
+            item.label = label;
+        }
+        Ok(())
+    }
+
+    /// Populates `hmenu` if it is a lazy submenu. Meant to be called on `WM_INITMENUPOPUP`, right
+    /// before the submenu is shown.
+    pub fn populate_lazy_menu(&mut self, hmenu: HMENU) {
+        let (menu, children) = match self.lazy_menus.get_mut(&hmenu) {
+            Some(lazy) => ((lazy.builder)(), mem::take(&mut lazy.children)),
+            None => return,
+        };
+
+        for child in children {
+            forget_lazy_menu(&mut self.lazy_menus, child);
+        }
+        forget_items(&mut self.items, hmenu);
+        unsafe {
+            // Deleting an item holding a submenu also destroys the submenu.
+            while GetMenuItemCount(hmenu) > 0 {
+                if DeleteMenu(hmenu, 0, MF_BYPOSITION) == false.into() {
+                    break;
+                }
+            }
+        }
+
+        let mut children = Vec::new();
+        if let Err(err) = append_entries(
+            hmenu,
+            menu,
+            &mut self.lazy_menus,
+            &mut self.items,
+            &mut children,
+        ) {
+            warn!("Failed to populate lazy tray submenu: {err}");
+        }
+        if let Some(lazy) = self.lazy_menus.get_mut(&hmenu) {
+            lazy.children = children;
+        }
+    }
+}
+
+impl Drop for MenuState {
+    fn drop(&mut self) {
+        if self.owned {
+            // Destroys the submenus as well.
+            unsafe { DestroyMenu(self.hmenu) };
+        }
+    }
+}
+
+/// Shows `hmenu` at `position` and blocks until it is dismissed.
+///
+/// A menu opened `from_keyboard` has its first item highlighted so it can be navigated right
+/// away. Selected items are reported to `window` through `WM_MENUCOMMAND`.
+pub fn show_menu(
+    window: HWND,
+    hmenu: HMENU,
+    position: PhysicalPosition<i32>,
+    from_keyboard: bool,
+) {
+    let horizontal_alignment = if unsafe { GetSystemMetrics(SM_MENUDROPALIGNMENT) } != 0 {
+        TPM_RIGHTALIGN
+    } else {
+        TPM_LEFTALIGN
+    };
+    let mut flags = horizontal_alignment | TPM_BOTTOMALIGN | TPM_RIGHTBUTTON;
+    if from_keyboard {
+        flags |= TPM_VERPOSANIMATION;
+        // Handled by the modal loop of the menu once it is shown.
+        unsafe { PostMessageW(window, WM_KEYDOWN, VK_DOWN as WPARAM, 0) };
+    }
+
+    unsafe {
+        // The menu isn't dismissed when clicking outside of it unless the owner window is in the
+        // foreground, and the posted message makes sure the next click is handled correctly. See
+        // https://learn.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-trackpopupmenu#remarks
+        SetForegroundWindow(window);
+        TrackPopupMenuEx(
+            hmenu,
+            flags,
+            position.x,
+            position.y,
+            window,
+            ptr::null(),
+        );
+        PostMessageW(window, WM_NULL, 0, 0);
+    }
+}
+
+/// Estimates the size `hmenu` is shown at from the metrics of menus, as the size of a menu is
+/// only known to the system once it is shown.
+fn measure_menu(hmenu: HMENU) -> Result<PhysicalSize<u32>, io::Error> {
+    let mut metrics = unsafe { mem::zeroed::<NONCLIENTMETRICSW>() };
+    metrics.cbSize = mem::size_of::<NONCLIENTMETRICSW>() as u32;
+    if unsafe {
+        SystemParametersInfoW(
+            SPI_GETNONCLIENTMETRICS,
+            metrics.cbSize,
+            &mut metrics as *mut _ as *mut _,
+            0,
+        )
+    } == false.into()
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    let item_height = unsafe { GetSystemMetrics(SM_CYMENU) };
+    // The check mark and the submenu arrow are on each side of the labels.
+    let margins = 2 * unsafe { GetSystemMetrics(SM_CXMENUCHECK) };
+    let (mut width, mut height) = (0, 0);
+    unsafe {
+        let hdc = GetDC(0);
+        let font = CreateFontIndirectW(&metrics.lfMenuFont);
+        let previous_font = SelectObject(hdc, font);
+        for position in 0..GetMenuItemCount(hmenu).max(0) {
+            let mut info = mem::zeroed::<MENUITEMINFOW>();
+            info.cbSize = mem::size_of::<MENUITEMINFOW>() as u32;
+            info.fMask = MIIM_FTYPE;
+            GetMenuItemInfoW(hmenu, position as u32, true.into(), &mut info);
+            if info.fType & MFT_SEPARATOR != 0 {
+                // Separators are about half as high as items.
+                height += item_height / 2;
+                continue;
+            }
+
+            let mut label = [0u16; 256];
+            let len = GetMenuStringW(
+                hmenu,
+                position as u32,
+                label.as_mut_ptr(),
+                label.len() as i32,
+                MF_BYPOSITION,
+            );
+            let mut extent = mem::zeroed::<SIZE>();
+            GetTextExtentPoint32W(hdc, label.as_ptr(), len, &mut extent);
+            width = width.max(extent.cx + margins);
+            height += item_height.max(extent.cy);
+        }
+        SelectObject(hdc, previous_font);
+        DeleteObject(font);
+        ReleaseDC(0, hdc);
+    }
+
+    let border_x = unsafe { GetSystemMetrics(SM_CXEDGE) };
+    let border_y = unsafe { GetSystemMetrics(SM_CYEDGE) };
+    Ok(PhysicalSize::new(
+        (width + 2 * border_x) as u32,
+        (height + 2 * border_y) as u32,
+    ))
+}
+
+fn create_popup_menu() -> Result<HMENU, io::Error> {
+    let hmenu = unsafe { CreatePopupMenu() };
+    if hmenu == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Items are reported by position through `WM_MENUCOMMAND`, which gives us the `HMENU` they
+    // belong to instead of truncating their identifier to 16 bits like `WM_COMMAND` does.
+    let mut info = unsafe { mem::zeroed::<MENUINFO>() };
+    info.cbSize = mem::size_of::<MENUINFO>() as u32;
+    info.fMask = MIM_STYLE;
+    info.dwStyle = MNS_NOTIFYBYPOS;
+    if unsafe { SetMenuInfo(hmenu, &info) } == false.into() {
+        let err = io::Error::last_os_error();
+        unsafe { DestroyMenu(hmenu) };
+        return Err(err);
+    }
+
+    Ok(hmenu)
+}
+
+/// Appends the entries of `menu` to `hmenu`, registering the lazy submenus it contains in
+/// `lazy_menus` and `created`, and its items in `items`.
+fn append_entries(
+    hmenu: HMENU,
+    menu: TrayMenu,
+    lazy_menus: &mut HashMap<HMENU, LazyMenu>,
+    items: &mut HashMap<MenuId, MenuItem>,
+    created: &mut Vec<HMENU>,
+) -> Result<(), io::Error> {
+    for entry in menu.entries {
+        let result = match entry {
+            MenuEntry::Item { id, label, data } => unsafe {
+                let wide_label = util::encode_wide(&label);
+                items.insert(id, MenuItem { label, data });
+                AppendMenuW(hmenu, MF_STRING, id.0 as usize, wide_label.as_ptr())
+            },
+            MenuEntry::Status { id, label } => unsafe {
+                let wide_label = util::encode_wide(&label);
+                items.insert(id, MenuItem { label, data: None });
+                AppendMenuW(hmenu, MF_STRING | MF_GRAYED, id.0 as usize, wide_label.as_ptr())
+            },
+            MenuEntry::Separator => unsafe { AppendMenuW(hmenu, MF_SEPARATOR, 0, ptr::null()) },
+            MenuEntry::Submenu { label, menu } => {
+                let submenu = create_popup_menu()?;
+                // Registered before being populated so it is destroyed on failure.
+                append_submenu(hmenu, submenu, &label)?;
+                append_entries(submenu, menu, lazy_menus, items, created)?;
+                continue;
+            }
+            MenuEntry::LazySubmenu { label, builder } => {
+                let submenu = create_popup_menu()?;
+                append_submenu(hmenu, submenu, &label)?;
+                lazy_menus.insert(
+                    submenu,
+                    LazyMenu {
+                        builder,
+                        children: Vec::new(),
+                    },
+                );
+                created.push(submenu);
+                continue;
+            }
+        };
+
+        if result == false.into() {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+fn append_submenu(hmenu: HMENU, submenu: HMENU, label: &str) -> Result<(), io::Error> {
+    let label = util::encode_wide(label);
+    if unsafe { AppendMenuW(hmenu, MF_POPUP | MF_STRING, submenu as usize, label.as_ptr()) }
+        == false.into()
+    {
+        let err = io::Error::last_os_error();
+        unsafe { DestroyMenu(submenu) };
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+/// Registers the items of the existing menu `hmenu` and its submenus in `items`.
+fn read_items(hmenu: HMENU, items: &mut HashMap<MenuId, MenuItem>) -> Result<(), io::Error> {
+    for position in 0..unsafe { GetMenuItemCount(hmenu) }.max(0) {
+        let mut info = unsafe { mem::zeroed::<MENUITEMINFOW>() };
+        info.cbSize = mem::size_of::<MENUITEMINFOW>() as u32;
+        info.fMask = MIIM_FTYPE | MIIM_ID | MIIM_STRING | MIIM_SUBMENU;
+        // Without a buffer, only the length of the label is retrieved.
+        if unsafe { GetMenuItemInfoW(hmenu, position as u32, true.into(), &mut info) }
+            == false.into()
+        {
+            return Err(io::Error::last_os_error());
+        }
+        if info.hSubMenu != 0 {
+            read_items(info.hSubMenu, items)?;
+            continue;
+        }
+        if info.fType & MFT_SEPARATOR != 0 {
+            continue;
+        }
+
+        let mut label = vec![0; info.cch as usize + 1];
+        info.dwTypeData = label.as_mut_ptr();
+        info.cch += 1;
+        if unsafe { GetMenuItemInfoW(hmenu, position as u32, true.into(), &mut info) }
+            == false.into()
+        {
+            return Err(io::Error::last_os_error());
+        }
+        let label = String::from_utf16_lossy(&label[..info.cch as usize]);
+        items.insert(MenuId(info.wID), MenuItem { label, data: None });
+    }
+
+    Ok(())
+}
+
+/// Removes the items of `hmenu` and its submenus from `items`.
+fn forget_items(items: &mut HashMap<MenuId, MenuItem>, hmenu: HMENU) {
+    for position in 0..unsafe { GetMenuItemCount(hmenu) }.max(0) {
+        let submenu = unsafe { GetSubMenu(hmenu, position) };
+        if submenu != 0 {
+            forget_items(items, submenu);
+        } else {
+            items.remove(&MenuId(unsafe { GetMenuItemID(hmenu, position) }));
+        }
+    }
+}
+
+fn forget_lazy_menu(lazy_menus: &mut HashMap<HMENU, LazyMenu>, hmenu: HMENU) {
+    if let Some(lazy) = lazy_menus.remove(&hmenu) {
+        for child in lazy.children {
+            forget_lazy_menu(lazy_menus, child);
+        }
+    }
+}
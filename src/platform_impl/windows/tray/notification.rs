@@ -0,0 +1,448 @@
+use std::mem;
+
+use windows_sys::Win32::{
+    Foundation::{ERROR_FILE_NOT_FOUND, HWND, S_OK},
+    Media::Audio::{PlaySoundW, SND_ALIAS, SND_ASYNC, SND_FILENAME},
+    System::Registry::HKEY_CURRENT_USER,
+    UI::Shell::{
+        SHQueryUserNotificationState, NIF_INFO, NIIF_LARGE_ICON, NIIF_NONE, NIIF_NOSOUND,
+        NIIF_USER, NIM_MODIFY, NOTIFYICONDATAW, QUNS_ACCEPTS_NOTIFICATIONS,
+    },
+};
+
+use super::startup;
+use crate::{
+    error::OsError as RootOsError,
+    platform_impl::platform::util,
+    tray::{NotificationBuilder, NotificationSetting, SoundSource},
+};
+
+/// Where the setting disabling all notifications of the user is stored.
+const PUSH_NOTIFICATIONS_KEY: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\PushNotifications";
+
+/// Called on the event loop thread when a balloon is clicked.
+pub type ClickCallback = Box<dyn FnOnce() + Send>;
+
+/// Whether the user is in a state where notifications shouldn't be shown, like quiet hours, a
+/// presentation or a full-screen application.
+pub fn is_quiet_time() -> bool {
+    let mut state = 0;
+    unsafe { SHQueryUserNotificationState(&mut state) == S_OK }
+        && state != QUNS_ACCEPTS_NOTIFICATIONS
+}
+
+/// Whether balloons are shown, which is only known when all notifications are disabled.
+pub fn balloon_setting() -> NotificationSetting {
+    let subkey = util::encode_wide(PUSH_NOTIFICATIONS_KEY);
+    let value = util::encode_wide("ToastEnabled");
+    match startup::read_dword(HKEY_CURRENT_USER, &subkey, &value) {
+        Ok(0) => NotificationSetting::Disabled,
+        Ok(_) => NotificationSetting::Enabled,
+        // Notifications are enabled until the user changes the setting.
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => {
+            NotificationSetting::Enabled
+        }
+        Err(_) => NotificationSetting::Unknown,
+    }
+}
+
+/// Shows `notification` as a balloon of the icon `uid` of `window`.
+///
+/// Each icon has at most one balloon, so it replaces any balloon the icon is showing. The sound of
+/// the notification is played once the balloon is shown.
+pub fn show_balloon(
+    window: HWND,
+    uid: u32,
+    notification: &NotificationBuilder,
+    retries: u32,
+) -> Result<(), RootOsError> {
+    let mut nid = unsafe { mem::zeroed::<NOTIFYICONDATAW>() };
+    nid.cbSize = mem::size_of::<NOTIFYICONDATAW>() as u32;
+    nid.hWnd = window;
+    nid.uID = uid;
+    // Only `NIF_INFO`, so that the icon of the tray is left untouched even when the balloon has
+    // its own.
+    nid.uFlags = NIF_INFO;
+    nid.dwInfoFlags = NIIF_NONE;
+    if let Some(icon) = &notification.icon {
+        nid.dwInfoFlags = NIIF_USER | NIIF_LARGE_ICON;
+        nid.hBalloonIcon = icon.inner.as_raw_handle();
+    }
+    // The shell only plays the default sound, so a custom one is played by us instead.
+    if notification.silent || notification.sound.is_some() {
+        nid.dwInfoFlags |= NIIF_NOSOUND;
+    }
+    // The fields are assigned as a whole since the struct is packed on x86.
+    nid.szInfoTitle = to_wide_array(&notification.title);
+    // The shell doesn't show a balloon without text.
+    nid.szInfo = to_wide_array(if notification.body.is_empty() {
+        " "
+    } else {
+        &notification.body
+    });
+
+    super::shell_notify_retry(NIM_MODIFY, &nid, retries).map_err(|err| os_error!(err))?;
+
+    if let (false, Some(sound)) = (notification.silent, &notification.sound) {
+        play_sound(sound);
+    }
+    Ok(())
+}
+
+/// Starts playing `sound`, or the default sound of the system if it can't be found.
+pub fn play_sound(sound: &SoundSource) {
+    let (name, flags) = match sound {
+        SoundSource::Alias(alias) => (util::encode_wide(alias), SND_ALIAS),
+        SoundSource::Path(path) => (util::encode_wide(path), SND_FILENAME),
+    };
+    if unsafe { PlaySoundW(name.as_ptr(), 0, flags | SND_ASYNC) } == false.into() {
+        warn!("Failed to play the notification sound");
+    }
+}
+
+/// Encodes `string` into a null terminated array, truncating it if needed.
+fn to_wide_array<const N: usize>(string: &str) -> [u16; N] {
+    let mut array = [0; N];
+    for (dst, src) in array.iter_mut().take(N - 1).zip(string.encode_utf16()) {
+        *dst = src;
+    }
+    array
+}
+
+#[cfg(feature = "tray-toast")]
+pub use self::toast::{show_toast, toast_setting, update_toast_progress};
+
+#[cfg(feature = "tray-toast")]
+mod toast {
+    use std::{
+        env, fs, mem,
+        path::PathBuf,
+        process,
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc, Mutex, Once,
+        },
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use windows::{
+        core::{ComInterface, IInspectable, Result, HSTRING},
+        Data::Xml::Dom::XmlDocument,
+        Foundation::{DateTime, IReference, PropertyValue, TypedEventHandler},
+        UI::Notifications::{
+            NotificationData, NotificationSetting, ToastActivatedEventArgs, ToastDismissalReason,
+            ToastDismissedEventArgs, ToastFailedEventArgs, ToastNotification,
+            ToastNotificationManager,
+        },
+    };
+
+    use crate::tray::{
+        NotificationBuilder, NotificationImage, NotificationSetting as Setting, SoundSource,
+    };
+
+    /// Numbers the temporary files of images, which are named after the process.
+    static NEXT_IMAGE_FILE: AtomicU32 = AtomicU32::new(0);
+    /// The prefix of the temporary files of images.
+    const IMAGE_FILE_PREFIX: &str = "winit-toast-";
+    /// How long toasts stay in the Action Center at most, after which the files of their images
+    /// aren't needed anymore.
+    const ACTION_CENTER_RETENTION: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+    /// A temporary file holding an image given as bytes, removed when dropped.
+    struct ImageFile(PathBuf);
+
+    impl Drop for ImageFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Shows `notification` as a toast of the application `app_user_model_id`.
+    ///
+    /// Toasts of a tray are put in a group named after its `uid`, so that tags only replace
+    /// toasts of the same tray.
+    ///
+    /// `on_action` is called with the id of the pressed action button, from a thread of the
+    /// shell.
+    pub fn show_toast<F>(
+        app_user_model_id: &str,
+        uid: u32,
+        notification: &NotificationBuilder,
+        on_action: F,
+    ) -> Result<()>
+    where
+        F: Fn(String) + Send + 'static,
+    {
+        static REMOVE_STALE_IMAGE_FILES: Once = Once::new();
+        REMOVE_STALE_IMAGE_FILES.call_once(remove_stale_image_files);
+
+        let mut image_files = Vec::new();
+        let image = image_uri(notification.image.as_ref(), &mut image_files);
+        let hero_image = image_uri(notification.hero_image.as_ref(), &mut image_files);
+
+        let xml = XmlDocument::new()?;
+        xml.LoadXml(&HSTRING::from(toast_xml(
+            notification,
+            image.as_deref(),
+            hero_image.as_deref(),
+        )))?;
+
+        let toast = ToastNotification::CreateToastNotification(&xml)?;
+        if !image_files.is_empty() {
+            // The toast may be activated instead of being dismissed.
+            let image_files = Arc::new(Mutex::new(image_files));
+            let files = image_files.clone();
+            toast.Dismissed(&TypedEventHandler::new(
+                move |_: &Option<ToastNotification>, args: &Option<ToastDismissedEventArgs>| {
+                    let reason = match args {
+                        Some(args) => args.Reason()?,
+                        None => ToastDismissalReason::UserCanceled,
+                    };
+                    let mut files = files.lock().unwrap();
+                    if reason == ToastDismissalReason::TimedOut {
+                        // The toast is still listed in the Action Center, which shows its images
+                        // from there. The files are removed by a later run instead.
+                        files.drain(..).for_each(mem::forget);
+                    } else {
+                        files.clear();
+                    }
+                    Ok(())
+                },
+            ))?;
+            let files = image_files.clone();
+            toast.Failed(&TypedEventHandler::new(
+                move |_: &Option<ToastNotification>, _: &Option<ToastFailedEventArgs>| {
+                    files.lock().unwrap().clear();
+                    Ok(())
+                },
+            ))?;
+            toast.Activated(&TypedEventHandler::new(
+                move |_: &Option<ToastNotification>, _: &Option<IInspectable>| {
+                    image_files.lock().unwrap().clear();
+                    Ok(())
+                },
+            ))?;
+        }
+        toast.SetGroup(&HSTRING::from(uid.to_string()))?;
+        if let Some(tag) = &notification.tag {
+            toast.SetTag(&HSTRING::from(tag.as_str()))?;
+        }
+        if let Some((_, value)) = notification.progress {
+            toast.SetData(&progress_data(value)?)?;
+        }
+        if let Some(duration) = notification.expires_after {
+            let expiration = date_time(SystemTime::now() + duration);
+            let expiration = PropertyValue::CreateDateTime(expiration)?;
+            toast.SetExpirationTime(&expiration.cast::<IReference<DateTime>>()?)?;
+        }
+        if !notification.actions.is_empty() {
+            // The arguments of a button are its id, while clicking the body has no arguments.
+            let handler = move |_: &Option<ToastNotification>, args: &Option<IInspectable>| {
+                if let Some(args) = args {
+                    let arguments = args.cast::<ToastActivatedEventArgs>()?.Arguments()?;
+                    if !arguments.is_empty() {
+                        on_action(arguments.to_string_lossy());
+                    }
+                }
+                Ok(())
+            };
+            toast.Activated(&TypedEventHandler::new(handler))?;
+        }
+
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_user_model_id))?
+            .Show(&toast)
+    }
+
+    /// Whether the toasts of the application `app_user_model_id` are shown.
+    pub fn toast_setting(app_user_model_id: &str) -> Setting {
+        let setting = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(
+            app_user_model_id,
+        ))
+        .and_then(|notifier| notifier.Setting());
+        match setting {
+            Ok(NotificationSetting::Enabled) => Setting::Enabled,
+            Ok(_) => Setting::Disabled,
+            Err(_) => Setting::Unknown,
+        }
+    }
+
+    /// Updates the progress bar of the toast tagged `tag` of the tray `uid`.
+    pub fn update_toast_progress(
+        app_user_model_id: &str,
+        uid: u32,
+        tag: &str,
+        value: f64,
+    ) -> Result<()> {
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_user_model_id))?
+            .UpdateWithTagAndGroup(
+                &progress_data(value)?,
+                &HSTRING::from(tag),
+                &HSTRING::from(uid.to_string()),
+            )?;
+        Ok(())
+    }
+
+    /// Removes the files of images of toasts that timed out in previous runs, once they can't be
+    /// listed in the Action Center anymore.
+    fn remove_stale_image_files() {
+        let entries = match fs::read_dir(env::temp_dir()) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        for entry in entries.flatten() {
+            let is_image = entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| name.starts_with(IMAGE_FILE_PREFIX));
+            let stale = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |age| age > ACTION_CENTER_RETENTION);
+            if is_image && stale {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Returns the URI of `image`, writing it to a temporary file pushed to `files` if needed.
+    ///
+    /// An image that can't be written is left out, rather than failing the whole toast.
+    fn image_uri(image: Option<&NotificationImage>, files: &mut Vec<ImageFile>) -> Option<String> {
+        let path = match image? {
+            NotificationImage::Path(path) if path.is_absolute() => path.clone(),
+            NotificationImage::Path(path) => env::current_dir().ok()?.join(path),
+            NotificationImage::Bytes(bytes) => {
+                let extension = match bytes.as_slice() {
+                    [0xFF, 0xD8, ..] => "jpg",
+                    [b'G', b'I', b'F', b'8', ..] => "gif",
+                    _ => "png",
+                };
+                let path = env::temp_dir().join(format!(
+                    "{IMAGE_FILE_PREFIX}{}-{}.{extension}",
+                    process::id(),
+                    NEXT_IMAGE_FILE.fetch_add(1, Ordering::Relaxed),
+                ));
+                if let Err(err) = fs::write(&path, bytes) {
+                    warn!("Failed to write the image of a toast: {err}");
+                    return None;
+                }
+                files.push(ImageFile(path.clone()));
+                path
+            }
+        };
+        Some(format!("file:///{}", path.display()))
+    }
+
+    /// Converts `time` to a WinRT `DateTime`, which counts 100 ns intervals since 1601.
+    fn date_time(time: SystemTime) -> DateTime {
+        const UNIX_EPOCH_SINCE_1601: Duration = Duration::from_secs(11_644_473_600);
+        let since_1601 =
+            time.duration_since(UNIX_EPOCH).unwrap_or_default() + UNIX_EPOCH_SINCE_1601;
+        DateTime {
+            UniversalTime: (since_1601.as_nanos() / 100) as i64,
+        }
+    }
+
+    /// Returns the values bound to the progress bar of a toast.
+    fn progress_data(value: f64) -> Result<NotificationData> {
+        let data = NotificationData::new()?;
+        data.Values()?.Insert(
+            &HSTRING::from("progressValue"),
+            &HSTRING::from(value.clamp(0.0, 1.0).to_string()),
+        )?;
+        Ok(data)
+    }
+
+    fn toast_xml(
+        notification: &NotificationBuilder,
+        image: Option<&str>,
+        hero_image: Option<&str>,
+    ) -> String {
+        let mut images = String::new();
+        if let Some(uri) = image {
+            images.push_str(&format!("<image src=\"{}\"/>", escape_xml(uri)));
+        }
+        if let Some(uri) = hero_image {
+            images.push_str(&format!(
+                "<image placement=\"hero\" src=\"{}\"/>",
+                escape_xml(uri)
+            ));
+        }
+        let progress = match &notification.progress {
+            Some((label, _)) => format!(
+                "<progress value=\"{{progressValue}}\" status=\"{}\"/>",
+                escape_xml(label)
+            ),
+            None => String::new(),
+        };
+        let mut actions = String::new();
+        if !notification.actions.is_empty() {
+            actions.push_str("<actions>");
+            for (id, label) in &notification.actions {
+                actions.push_str(&format!(
+                    "<action content=\"{}\" arguments=\"{}\" activationType=\"foreground\"/>",
+                    escape_xml(label),
+                    escape_xml(id),
+                ));
+            }
+            actions.push_str("</actions>");
+        }
+        // The group is used as the identifier of the header as well as its title.
+        let header = match &notification.group {
+            Some(group) => format!(
+                "<header id=\"{0}\" title=\"{0}\" arguments=\"\"/>",
+                escape_xml(group)
+            ),
+            None => String::new(),
+        };
+        let attribution = match &notification.attribution {
+            Some(attribution) => format!(
+                "<text placement=\"attribution\">{}</text>",
+                escape_xml(attribution)
+            ),
+            None => String::new(),
+        };
+        let audio = match (notification.silent, &notification.sound) {
+            (true, _) => "<audio silent=\"true\"/>".to_string(),
+            (false, Some(SoundSource::Alias(alias))) => format!(
+                "<audio src=\"ms-winsoundevent:{}\"/>",
+                escape_xml(alias)
+            ),
+            // The toast falls back to the default sound if the file can't be played.
+            (false, Some(SoundSource::Path(path))) => format!(
+                "<audio src=\"file:///{}\"/>",
+                escape_xml(&path.display().to_string())
+            ),
+            (false, None) => String::new(),
+        };
+        format!(
+            "<toast>{}<visual><binding template=\"ToastGeneric\"><text>{}</text><text>{}</text>{}{}{}</binding></visual>{}{}</toast>",
+            header,
+            escape_xml(&notification.title),
+            escape_xml(&notification.body),
+            attribution,
+            images,
+            progress,
+            actions,
+            audio,
+        )
+    }
+
+    fn escape_xml(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
@@ -0,0 +1,133 @@
+use std::{ffi::OsString, io, mem, os::windows::ffi::OsStringExt, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{ERROR_FILE_NOT_FOUND, ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS, HWND},
+    System::Registry::{
+        RegDeleteValueW, RegEnumValueW, RegSetValueExW, KEY_QUERY_VALUE, KEY_SET_VALUE, REG_SZ,
+    },
+    UI::WindowsAndMessaging::{GetClassNameW, IsWindow},
+};
+
+use super::startup::{self, RegKey};
+use crate::platform_impl::platform::util;
+
+/// Where the icons are recorded while they exist, as values named after the executable, the
+/// window and the uid of the icon, holding the class of the window.
+const ICONS_KEY: &str = "Software\\winit\\TrayIcons";
+
+pub fn record(window: HWND, uid: u32, class: &str) -> Result<(), io::Error> {
+    let key = RegKey::create_current_user(ICONS_KEY, KEY_SET_VALUE)?;
+    let name = util::encode_wide(value_name(&exe_name()?, window, uid));
+    let class = util::encode_wide(class);
+    let status = unsafe {
+        RegSetValueExW(
+            key.0,
+            name.as_ptr(),
+            0,
+            REG_SZ,
+            class.as_ptr() as *const u8,
+            (class.len() * mem::size_of::<u16>()) as u32,
+        )
+    };
+    startup::check(status)
+}
+
+pub fn forget(window: HWND, uid: u32) {
+    let (key, exe) = match (RegKey::open_current_user(ICONS_KEY, KEY_SET_VALUE), exe_name()) {
+        (Ok(key), Ok(exe)) => (key, exe),
+        _ => return,
+    };
+    let name = util::encode_wide(value_name(&exe, window, uid));
+    unsafe { RegDeleteValueW(key.0, name.as_ptr()) };
+}
+
+/// Removes the recorded icons of `class` created by this executable whose window no longer
+/// exists, returning how many the shell still showed.
+///
+/// Icons of other executables are left alone, even if their windows are of the same class.
+pub fn cleanup(class: &str) -> Result<usize, io::Error> {
+    let exe = exe_name()?;
+    let key = match RegKey::open_current_user(ICONS_KEY, KEY_QUERY_VALUE | KEY_SET_VALUE) {
+        Ok(key) => key,
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => return Ok(0),
+        Err(err) => return Err(err),
+    };
+
+    // Collected first, as deleting values while enumerating them shifts their indices.
+    let mut icons = Vec::new();
+    let mut name = [0u16; 1024];
+    let mut data = [0u16; 256];
+    for index in 0.. {
+        let mut name_len = name.len() as u32;
+        let mut data_size = mem::size_of_val(&data) as u32;
+        let mut kind = 0;
+        let status = unsafe {
+            RegEnumValueW(
+                key.0,
+                index,
+                name.as_mut_ptr(),
+                &mut name_len,
+                ptr::null(),
+                &mut kind,
+                data.as_mut_ptr() as *mut u8,
+                &mut data_size,
+            )
+        };
+        match status {
+            ERROR_SUCCESS => (),
+            ERROR_NO_MORE_ITEMS => break,
+            // Too long to be one of our values.
+            ERROR_MORE_DATA => continue,
+            status => return startup::check(status).map(|()| 0),
+        }
+        let data = &data[..data_size as usize / mem::size_of::<u16>()];
+        let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+        if kind != REG_SZ || OsString::from_wide(&data[..len]) != class {
+            continue;
+        }
+        let name = OsString::from_wide(&name[..name_len as usize]);
+        match name.to_str().and_then(parse_value_name) {
+            Some((icon_exe, window, uid)) if icon_exe == exe => icons.push((window, uid)),
+            _ => (),
+        }
+    }
+
+    let mut removed = 0;
+    for (window, uid) in icons {
+        // Window handles are reused, so the window must also still be of the same class.
+        if unsafe { IsWindow(window) } != false.into() && window_class(window) == class {
+            continue;
+        }
+        // Not retried, as the shell being busy doesn't make the icon any less orphaned.
+        if super::delete_icon(window, uid, 0) {
+            removed += 1;
+        }
+        // Also forgets the icons the shell already dropped on its own.
+        forget(window, uid);
+    }
+    Ok(removed)
+}
+
+fn window_class(window: HWND) -> String {
+    let mut class = [0u16; 256];
+    let len = unsafe { GetClassNameW(window, class.as_mut_ptr(), class.len() as i32) };
+    OsString::from_wide(&class[..len.max(0) as usize])
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn exe_name() -> Result<String, io::Error> {
+    Ok(startup::current_exe()?.to_string_lossy().into_owned())
+}
+
+/// Paths can't contain `|`, so it separates the executable from the rest.
+fn value_name(exe: &str, window: HWND, uid: u32) -> String {
+    format!("{exe}|{:x}:{uid}", window as usize)
+}
+
+fn parse_value_name(name: &str) -> Option<(&str, HWND, u32)> {
+    let (exe, icon) = name.rsplit_once('|')?;
+    let (window, uid) = icon.split_once(':')?;
+    let window = usize::from_str_radix(window, 16).ok()? as HWND;
+    Some((exe, window, uid.parse().ok()?))
+}
@@ -0,0 +1,130 @@
+use std::{ffi::OsString, io, mem, os::windows::ffi::OsStringExt, path::Path, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{ERROR_FILE_NOT_FOUND, ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS},
+    System::Registry::{
+        RegDeleteValueW, RegEnumValueW, RegGetValueW, RegSetValueExW, KEY_QUERY_VALUE,
+        KEY_SET_VALUE, REG_SZ, RRF_RT_REG_SZ,
+    },
+};
+
+use super::startup::{self, RegKey};
+use crate::{platform_impl::platform::util, tray::StateKey};
+
+/// Where the last status of the icons is kept across runs, as values named after the executable
+/// and the name given to the icon, holding the name of the status.
+const STATUS_KEY: &str = "Software\\winit\\TrayStatus";
+
+/// Saves `status` as the last status of the icon `name` of this executable, or forgets it if
+/// there is none.
+pub fn save_status(name: &str, status: Option<StateKey>) -> Result<(), io::Error> {
+    let key = RegKey::create_current_user(STATUS_KEY, KEY_SET_VALUE)?;
+    let name = util::encode_wide(value_name(&exe_name()?, name));
+    let status = match status {
+        Some(status) => util::encode_wide(status.0),
+        None => {
+            unsafe { RegDeleteValueW(key.0, name.as_ptr()) };
+            return Ok(());
+        }
+    };
+    let result = unsafe {
+        RegSetValueExW(
+            key.0,
+            name.as_ptr(),
+            0,
+            REG_SZ,
+            status.as_ptr() as *const u8,
+            (status.len() * mem::size_of::<u16>()) as u32,
+        )
+    };
+    startup::check(result)
+}
+
+/// Returns the name of the last status saved for the icon `name` of this executable.
+pub fn load_status(name: &str) -> Result<Option<String>, io::Error> {
+    let key = match RegKey::open_current_user(STATUS_KEY, KEY_QUERY_VALUE) {
+        Ok(key) => key,
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let name = util::encode_wide(value_name(&exe_name()?, name));
+    let mut data = [0u16; 256];
+    let mut size = mem::size_of_val(&data) as u32;
+    let result = unsafe {
+        RegGetValueW(
+            key.0,
+            ptr::null(),
+            name.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            data.as_mut_ptr() as *mut _,
+            &mut size,
+        )
+    };
+    match startup::check(result) {
+        Ok(()) => {
+            let data = &data[..size as usize / mem::size_of::<u16>()];
+            let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+            Ok(Some(String::from_utf16_lossy(&data[..len])))
+        }
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Forgets the statuses saved by executables that no longer exist, which can't be restored.
+pub fn prune() -> Result<(), io::Error> {
+    let key = match RegKey::open_current_user(STATUS_KEY, KEY_QUERY_VALUE | KEY_SET_VALUE) {
+        Ok(key) => key,
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    // Collected first, as deleting values while enumerating them shifts their indices.
+    let mut stale = Vec::new();
+    let mut name = [0u16; 1024];
+    for index in 0.. {
+        let mut name_len = name.len() as u32;
+        let status = unsafe {
+            RegEnumValueW(
+                key.0,
+                index,
+                name.as_mut_ptr(),
+                &mut name_len,
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        match status {
+            ERROR_SUCCESS => (),
+            ERROR_NO_MORE_ITEMS => break,
+            // Too long to be one of our values.
+            ERROR_MORE_DATA => continue,
+            status => return startup::check(status),
+        }
+        let value = OsString::from_wide(&name[..name_len as usize]);
+        let exists = match value.to_str().and_then(|value| value.split_once('|')) {
+            Some((exe, _)) => Path::new(exe).exists(),
+            None => false,
+        };
+        if !exists {
+            stale.push(util::encode_wide(value));
+        }
+    }
+
+    for name in stale {
+        unsafe { RegDeleteValueW(key.0, name.as_ptr()) };
+    }
+    Ok(())
+}
+
+fn exe_name() -> Result<String, io::Error> {
+    Ok(startup::current_exe()?.to_string_lossy().into_owned())
+}
+
+/// Paths can't contain `|`, so it separates the executable from the name of the icon.
+fn value_name(exe: &str, name: &str) -> String {
+    format!("{exe}|{name}")
+}
@@ -0,0 +1,209 @@
+use std::{
+    ffi::OsString, io, mem, os::windows::ffi::OsStringExt, ptr, sync::Once, thread,
+    time::Duration,
+};
+
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, ERROR_FILE_NOT_FOUND, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS},
+    System::{
+        Registry::{
+            RegEnumKeyExW, RegGetValueW, RegNotifyChangeKeyValue, RegSetKeyValueW, HKEY,
+            KEY_NOTIFY, KEY_READ, KEY_SET_VALUE, REG_DWORD, REG_NOTIFY_CHANGE_LAST_SET,
+            REG_NOTIFY_CHANGE_NAME, RRF_RT_REG_SZ,
+        },
+        Threading::{CreateEventW, WaitForSingleObject, INFINITE},
+    },
+};
+
+use super::startup::{self, RegKey};
+use crate::platform_impl::platform::util;
+
+/// Where Windows 11 records the icons it has seen and whether they are shown on the taskbar.
+const SETTINGS_KEY: &str = "Control Panel\\NotifyIconSettings";
+/// How long the settings must be still before they are reported as changed.
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/// Promotes the icons of the current executable out of the overflow area, unless the user already
+/// chose where they go.
+///
+/// The settings of an icon are only created by Explorer once it has seen the icon, so this has no
+/// effect on older versions of Windows or when they don't exist yet.
+pub fn promote_current_exe() -> Result<(), io::Error> {
+    let settings = match RegKey::open_current_user(SETTINGS_KEY, KEY_READ | KEY_SET_VALUE) {
+        Ok(settings) => settings,
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    let promoted_value = util::encode_wide("IsPromoted");
+    for subkey in current_exe_settings(&settings)? {
+        if startup::read_dword(settings.0, &subkey, &promoted_value).is_ok() {
+            continue;
+        }
+
+        let promoted = 1u32;
+        let status = unsafe {
+            RegSetKeyValueW(
+                settings.0,
+                subkey.as_ptr(),
+                promoted_value.as_ptr(),
+                REG_DWORD,
+                &promoted as *const u32 as *const _,
+                mem::size_of::<u32>() as u32,
+            )
+        };
+        startup::check(status)?;
+    }
+    Ok(())
+}
+
+/// Whether the icons of the current executable are shown on the taskbar rather than in the
+/// overflow area, or `None` if Explorer has no settings for them.
+pub fn current_exe_promoted() -> Result<Option<bool>, io::Error> {
+    let settings = match RegKey::open_current_user(SETTINGS_KEY, KEY_READ) {
+        Ok(settings) => settings,
+        Err(err) if err.raw_os_error() == Some(ERROR_FILE_NOT_FOUND as i32) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let promoted_value = util::encode_wide("IsPromoted");
+    let mut promoted = None;
+    for subkey in current_exe_settings(&settings)? {
+        // Icons the user didn't choose for go to the overflow area.
+        let value = startup::read_dword(settings.0, &subkey, &promoted_value).unwrap_or(0);
+        promoted = Some(promoted.unwrap_or(false) || value != 0);
+    }
+    Ok(promoted)
+}
+
+/// Calls `on_change` from a thread of its own shortly after the settings of the icons change,
+/// letting Explorer finish the several changes it makes in a row when the user moves an icon.
+///
+/// Only the first call starts watching, and nothing is watched on versions of Windows without
+/// these settings.
+pub fn watch_settings(on_change: fn()) {
+    static WATCH: Once = Once::new();
+    WATCH.call_once(|| {
+        let settings = match RegKey::open_current_user(SETTINGS_KEY, KEY_NOTIFY) {
+            Ok(settings) => settings,
+            Err(err) => {
+                if err.raw_os_error() != Some(ERROR_FILE_NOT_FOUND as i32) {
+                    warn!("Failed to watch the tray icon settings: {err}");
+                }
+                return;
+            }
+        };
+        let spawned = thread::Builder::new()
+            .name("winit tray settings".to_owned())
+            .spawn(move || {
+                if let Err(err) = watch(&settings, on_change) {
+                    warn!("Stopped watching the tray icon settings: {err}");
+                }
+            });
+        if let Err(err) = spawned {
+            warn!("Failed to watch the tray icon settings: {err}");
+        }
+    });
+}
+
+fn watch(settings: &RegKey, on_change: fn()) -> Result<(), io::Error> {
+    let event = unsafe { CreateEventW(ptr::null(), false.into(), false.into(), ptr::null()) };
+    if event == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut changed = false;
+    let result = loop {
+        // Registered again before reporting, so that no change goes unnoticed in between.
+        let status = unsafe {
+            RegNotifyChangeKeyValue(
+                settings.0,
+                true.into(),
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                event,
+                true.into(),
+            )
+        };
+        if let Err(err) = startup::check(status) {
+            break Err(err);
+        }
+        if mem::take(&mut changed) {
+            on_change();
+        }
+        unsafe { WaitForSingleObject(event, INFINITE) };
+        thread::sleep(SETTLE_DELAY);
+        changed = true;
+    };
+    unsafe { CloseHandle(event) };
+    result
+}
+
+/// Returns the subkeys of `settings` holding the settings of the icons of the current executable,
+/// null terminated.
+fn current_exe_settings(settings: &RegKey) -> Result<Vec<Vec<u16>>, io::Error> {
+    let exe = startup::current_exe()?.to_string_lossy().to_lowercase();
+    let path_value = util::encode_wide("ExecutablePath");
+    let mut subkeys = Vec::new();
+    let mut name = [0u16; 256];
+    for index in 0.. {
+        let mut len = name.len() as u32;
+        let status = unsafe {
+            RegEnumKeyExW(
+                settings.0,
+                index,
+                name.as_mut_ptr(),
+                &mut len,
+                ptr::null(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        match status {
+            ERROR_SUCCESS => (),
+            ERROR_NO_MORE_ITEMS => break,
+            status => startup::check(status)?,
+        }
+        let subkey: Vec<u16> = name[..len as usize].iter().copied().chain([0]).collect();
+
+        let matches = read_string(settings.0, &subkey, &path_value)
+            .map_or(false, |path| same_executable(&path.to_lowercase(), &exe));
+        if matches {
+            subkeys.push(subkey);
+        }
+    }
+    Ok(subkeys)
+}
+
+/// Whether the `ExecutablePath` of an icon designates `exe`, both being lowercase.
+///
+/// Paths under known folders like Program Files start with the GUID of the folder instead.
+fn same_executable(path: &str, exe: &str) -> bool {
+    if path == exe {
+        return true;
+    }
+    match (path.starts_with('{'), path.find('}')) {
+        (true, Some(end)) => exe.ends_with(&path[end + 1..]),
+        _ => false,
+    }
+}
+
+fn read_string(hkey: HKEY, subkey: &[u16], value: &[u16]) -> Option<String> {
+    let mut data = [0u16; 1024];
+    let mut size = mem::size_of_val(&data) as u32;
+    let status = unsafe {
+        RegGetValueW(
+            hkey,
+            subkey.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            data.as_mut_ptr() as *mut _,
+            &mut size,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+    let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+    Some(OsString::from_wide(&data[..len]).to_string_lossy().into_owned())
+}
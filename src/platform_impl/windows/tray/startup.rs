@@ -0,0 +1,171 @@
+use std::{ffi::OsString, io, mem, os::windows::ffi::OsStringExt, path::Path, ptr};
+
+use windows_sys::Win32::{
+    Foundation::{GetLastError, ERROR_FILE_NOT_FOUND, ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS},
+    System::{
+        LibraryLoader::GetModuleFileNameW,
+        Registry::{
+            RegCloseKey, RegCreateKeyExW, RegDeleteValueW, RegGetValueW, RegOpenKeyExW,
+            RegSetValueExW, HKEY, HKEY_CURRENT_USER, KEY_SET_VALUE, REG_OPTION_NON_VOLATILE,
+            REG_SAM_FLAGS, REG_SZ, RRF_RT_REG_DWORD, RRF_RT_REG_SZ,
+        },
+    },
+};
+
+use crate::platform_impl::platform::util;
+
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
+
+/// An open registry key, closed when dropped.
+pub struct RegKey(pub HKEY);
+
+impl RegKey {
+    /// Opens the existing `subkey` of `HKEY_CURRENT_USER`.
+    pub fn open_current_user(subkey: &str, access: REG_SAM_FLAGS) -> Result<RegKey, io::Error> {
+        let subkey = util::encode_wide(subkey);
+        let mut hkey = 0;
+        let status =
+            unsafe { RegOpenKeyExW(HKEY_CURRENT_USER, subkey.as_ptr(), 0, access, &mut hkey) };
+        check(status)?;
+        Ok(RegKey(hkey))
+    }
+
+    /// Opens `subkey` of `HKEY_CURRENT_USER`, creating it if needed.
+    pub fn create_current_user(subkey: &str, access: REG_SAM_FLAGS) -> Result<RegKey, io::Error> {
+        let subkey = util::encode_wide(subkey);
+        let mut hkey = 0;
+        let status = unsafe {
+            RegCreateKeyExW(
+                HKEY_CURRENT_USER,
+                subkey.as_ptr(),
+                0,
+                ptr::null(),
+                REG_OPTION_NON_VOLATILE,
+                access,
+                ptr::null(),
+                &mut hkey,
+                ptr::null_mut(),
+            )
+        };
+        check(status)?;
+        Ok(RegKey(hkey))
+    }
+}
+
+impl Drop for RegKey {
+    fn drop(&mut self) {
+        unsafe { RegCloseKey(self.0) };
+    }
+}
+
+pub fn set_run_at_startup(enabled: bool) -> Result<(), io::Error> {
+    let (name, command) = startup_entry()?;
+    let key = RegKey::create_current_user(RUN_KEY, KEY_SET_VALUE)?;
+
+    let status = if enabled {
+        let command = util::encode_wide(&command);
+        unsafe {
+            RegSetValueExW(
+                key.0,
+                name.as_ptr(),
+                0,
+                REG_SZ,
+                command.as_ptr() as *const u8,
+                (command.len() * mem::size_of::<u16>()) as u32,
+            )
+        }
+    } else {
+        match unsafe { RegDeleteValueW(key.0, name.as_ptr()) } {
+            // The application wasn't registered in the first place.
+            ERROR_FILE_NOT_FOUND => ERROR_SUCCESS,
+            status => status,
+        }
+    };
+    check(status)
+}
+
+/// Whether the `Run` key starts the current executable, rather than another copy of it.
+pub fn runs_at_startup() -> bool {
+    let (name, command) = match startup_entry() {
+        Ok(entry) => entry,
+        Err(_) => return false,
+    };
+    let subkey = util::encode_wide(RUN_KEY);
+
+    let mut size = 0;
+    let query = |data: *mut u16, size: &mut u32| unsafe {
+        RegGetValueW(
+            HKEY_CURRENT_USER,
+            subkey.as_ptr(),
+            name.as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            data as *mut _,
+            size,
+        )
+    };
+    if query(ptr::null_mut(), &mut size) != ERROR_SUCCESS {
+        return false;
+    }
+    let mut value = vec![0u16; size as usize / mem::size_of::<u16>()];
+    if query(value.as_mut_ptr(), &mut size) != ERROR_SUCCESS {
+        return false;
+    }
+
+    let len = value.iter().position(|&c| c == 0).unwrap_or(value.len());
+    OsString::from_wide(&value[..len]) == OsString::from(command)
+}
+
+/// Returns the name of the `Run` value of the current executable, null terminated, along with
+/// the command line starting it.
+fn startup_entry() -> Result<(Vec<u16>, String), io::Error> {
+    let path = current_exe()?;
+    let name = Path::new(&path)
+        .file_stem()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Invalid executable path"))?;
+    let command = format!("\"{}\"", path.to_string_lossy());
+    Ok((util::encode_wide(name), command))
+}
+
+pub fn current_exe() -> Result<OsString, io::Error> {
+    let mut buffer = vec![0u16; 260];
+    loop {
+        let len = unsafe { GetModuleFileNameW(0, buffer.as_mut_ptr(), buffer.len() as u32) };
+        if len == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // The path is truncated when the buffer is too small.
+        if len as usize == buffer.len() && unsafe { GetLastError() } == ERROR_INSUFFICIENT_BUFFER
+        {
+            buffer.resize(buffer.len() * 2, 0);
+            continue;
+        }
+        return Ok(OsString::from_wide(&buffer[..len as usize]));
+    }
+}
+
+/// Reads the DWORD `value` of `subkey` of `hkey`, both null terminated.
+pub fn read_dword(hkey: HKEY, subkey: &[u16], value: &[u16]) -> Result<u32, io::Error> {
+    let mut data = 0u32;
+    let mut size = mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            hkey,
+            subkey.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_DWORD,
+            ptr::null_mut(),
+            &mut data as *mut u32 as *mut _,
+            &mut size,
+        )
+    };
+    check(status).map(|()| data)
+}
+
+pub fn check(status: u32) -> Result<(), io::Error> {
+    if status == ERROR_SUCCESS {
+        Ok(())
+    } else {
+        Err(io::Error::from_raw_os_error(status as i32))
+    }
+}
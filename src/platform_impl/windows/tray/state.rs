@@ -0,0 +1,158 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use windows_sys::Win32::UI::WindowsAndMessaging::HICON;
+
+use crate::{
+    dpi::PhysicalSize,
+    platform_impl::platform::WinIcon,
+    tray::{PhysicalRect, StateKey, StatusIconSet},
+    window::{BadIcon, Icon},
+};
+
+use super::tint;
+#[cfg(feature = "tray-notifications")]
+use {super::notification::ClickCallback, crate::tray::NotificationBuilder};
+
+pub(crate) enum BaseIcon {
+    Custom(Icon),
+    /// A shared icon loaded from resources, which must not be destroyed.
+    Shared(HICON),
+    /// An icon loaded from an `.ico` file at `size`, reloaded when the size changes to pick the
+    /// best frame of the file.
+    File {
+        path: PathBuf,
+        size: PhysicalSize<u32>,
+        icon: Icon,
+    },
+}
+
+/// Contains the state of a tray icon, shared between the `Tray` and its window callback.
+pub(crate) struct TrayState {
+    base_icon: BaseIcon,
+    tint: Option<[u8; 4]>,
+    /// Tinted versions of the base icon, so switching between colors renders each of them once.
+    tinted_icons: HashMap<[u8; 4], WinIcon>,
+    /// Icons rendered ahead of time for each status, and the one currently shown if any, which
+    /// takes precedence over the base icon and its tint.
+    status_icons: Option<StatusIconSet>,
+    status: Option<StateKey>,
+    /// The name the status is saved under across runs and the status last saved, if it is saved.
+    persisted_status: Option<(String, Option<StateKey>)>,
+    /// The last known rectangle of the icon, to report when it moves.
+    pub icon_rect: Option<PhysicalRect>,
+    /// The notifications withheld during quiet time, oldest first.
+    #[cfg(feature = "tray-notifications")]
+    pub pending_notifications: Vec<(NotificationBuilder, Option<ClickCallback>)>,
+}
+
+impl TrayState {
+    pub fn new(base_icon: BaseIcon, status_icons: Option<StatusIconSet>) -> TrayState {
+        TrayState {
+            base_icon,
+            tint: None,
+            tinted_icons: HashMap::new(),
+            status_icons,
+            status: None,
+            persisted_status: None,
+            icon_rect: None,
+            #[cfg(feature = "tray-notifications")]
+            pending_notifications: Vec::new(),
+        }
+    }
+
+    pub fn set_icon(&mut self, icon: Icon) {
+        self.base_icon = BaseIcon::Custom(icon);
+        self.tinted_icons.clear();
+        self.status = None;
+    }
+
+    pub fn set_tint(&mut self, tint: Option<[u8; 4]>) {
+        self.tint = tint;
+        self.status = None;
+    }
+
+    /// Returns whether the state has an icon for the status `key`.
+    pub fn has_status(&self, key: StateKey) -> bool {
+        self.status_icons
+            .as_ref()
+            .map_or(false, |icons| icons.get(key).is_some())
+    }
+
+    /// Reloads the icon file at `size`, if the icon comes from a file of another size.
+    pub fn reload_icon_file(&mut self, new_size: PhysicalSize<u32>) {
+        if let BaseIcon::File { path, size, icon } = &mut self.base_icon {
+            if *size == new_size {
+                return;
+            }
+            match super::load_icon_file(path, new_size) {
+                Ok(new_icon) => {
+                    *size = new_size;
+                    *icon = new_icon;
+                    self.tinted_icons.clear();
+                }
+                Err(err) => warn!("Failed to reload the tray icon from {path:?}: {err}"),
+            }
+        }
+    }
+
+    /// Shows the icon of the status `key`, which must exist.
+    pub fn set_status(&mut self, key: StateKey) {
+        self.status = Some(key);
+    }
+
+    /// Shows the status named `status` if there is an icon for it, and saves the status across
+    /// runs under `name` from now on. Returns the status shown.
+    pub fn restore_status(&mut self, name: String, status: Option<&str>) -> Option<StateKey> {
+        self.status = status.and_then(|status| self.status_icons.as_ref()?.key(status));
+        self.persisted_status = Some((name, self.status));
+        self.status
+    }
+
+    /// Returns the name to save the status under and the status, if it is saved and changed
+    /// since it last was.
+    pub fn unsaved_status(&mut self) -> Option<(&str, Option<StateKey>)> {
+        let status = self.status;
+        match &mut self.persisted_status {
+            Some((name, saved)) if *saved != status => {
+                *saved = status;
+                Some((name, status))
+            }
+            _ => None,
+        }
+    }
+
+    /// Releases the icons once the window of the tray is destroyed.
+    pub fn release(&mut self) {
+        self.base_icon = BaseIcon::Shared(0);
+        self.tinted_icons.clear();
+        self.status_icons = None;
+        self.status = None;
+    }
+
+    /// Returns the icon to show, rendering its tinted version if needed.
+    pub fn displayed_icon(&mut self) -> Result<HICON, BadIcon> {
+        let status_icon = self
+            .status
+            .and_then(|key| self.status_icons.as_ref()?.get(key));
+        if let Some(icon) = status_icon {
+            return Ok(icon.inner.as_raw_handle());
+        }
+
+        let base_icon = match &self.base_icon {
+            BaseIcon::Custom(icon) | BaseIcon::File { icon, .. } => icon.inner.as_raw_handle(),
+            BaseIcon::Shared(icon) => *icon,
+        };
+        let color = match self.tint {
+            Some(color) => color,
+            None => return Ok(base_icon),
+        };
+
+        if let Some(icon) = self.tinted_icons.get(&color) {
+            return Ok(icon.as_raw_handle());
+        }
+        let icon = tint::tint_icon(base_icon, color)?;
+        let handle = icon.as_raw_handle();
+        self.tinted_icons.insert(color, icon);
+        Ok(handle)
+    }
+}
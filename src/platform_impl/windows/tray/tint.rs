@@ -0,0 +1,107 @@
+use std::{ffi::c_void, io, mem};
+
+use windows_sys::Win32::{
+    Graphics::Gdi::{
+        DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
+        BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS, HBITMAP,
+    },
+    UI::WindowsAndMessaging::{GetIconInfo, HICON, ICONINFO},
+};
+
+use crate::{platform_impl::platform::WinIcon, window::BadIcon};
+
+/// Renders a copy of `icon` with each of its channels multiplied by the matching one of `color`.
+pub fn tint_icon(icon: HICON, color: [u8; 4]) -> Result<WinIcon, BadIcon> {
+    let (mut rgba, width, height) = icon_pixels(icon).map_err(BadIcon::OsError)?;
+    for pixel in rgba.chunks_exact_mut(4) {
+        for (channel, tint) in pixel.iter_mut().zip(color) {
+            *channel = (*channel as u16 * tint as u16 / 255) as u8;
+        }
+    }
+    WinIcon::from_rgba(rgba, width, height)
+}
+
+/// Returns the RGBA pixels of `icon` along with its size.
+fn icon_pixels(icon: HICON) -> Result<(Vec<u8>, u32, u32), io::Error> {
+    let mut info = unsafe { mem::zeroed::<ICONINFO>() };
+    if unsafe { GetIconInfo(icon, &mut info) } == false.into() {
+        return Err(io::Error::last_os_error());
+    }
+
+    let result = read_icon_pixels(&info);
+    // The bitmaps are copies owned by the caller of `GetIconInfo`.
+    unsafe {
+        DeleteObject(info.hbmColor);
+        DeleteObject(info.hbmMask);
+    }
+    result
+}
+
+fn read_icon_pixels(info: &ICONINFO) -> Result<(Vec<u8>, u32, u32), io::Error> {
+    if info.hbmColor == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Monochrome icons can't be tinted",
+        ));
+    }
+
+    let mut bitmap = unsafe { mem::zeroed::<BITMAP>() };
+    if unsafe {
+        GetObjectW(
+            info.hbmColor,
+            mem::size_of::<BITMAP>() as i32,
+            &mut bitmap as *mut BITMAP as *mut c_void,
+        )
+    } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+    let (width, height) = (bitmap.bmWidth, bitmap.bmHeight);
+
+    let mut pixels = bitmap_bits(info.hbmColor, width, height)?;
+    if pixels.chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        // Icons without an alpha channel rely on their mask for transparency.
+        let mask = bitmap_bits(info.hbmMask, width, height)?;
+        for (pixel, mask) in pixels.chunks_exact_mut(4).zip(mask.chunks_exact(4)) {
+            pixel[3] = if mask[0] == 0 { u8::MAX } else { 0 };
+        }
+    }
+    for pixel in pixels.chunks_exact_mut(4) {
+        // BGRA to RGBA.
+        pixel.swap(0, 2);
+    }
+
+    Ok((pixels, width as u32, height as u32))
+}
+
+/// Returns the pixels of `bitmap` as top-down, 32 bits BGRA.
+fn bitmap_bits(bitmap: HBITMAP, width: i32, height: i32) -> Result<Vec<u8>, io::Error> {
+    let mut info = unsafe { mem::zeroed::<BITMAPINFO>() };
+    info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as u32;
+    info.bmiHeader.biWidth = width;
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = BI_RGB;
+
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+    let lines = unsafe {
+        let dc = GetDC(0);
+        let lines = GetDIBits(
+            dc,
+            bitmap,
+            0,
+            height as u32,
+            pixels.as_mut_ptr() as *mut c_void,
+            &mut info,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(0, dc);
+        lines
+    };
+    if lines == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(pixels)
+}
@@ -13,7 +13,7 @@ use once_cell::sync::Lazy;
 use windows_sys::{
     core::{HRESULT, PCWSTR},
     Win32::{
-        Foundation::{BOOL, HANDLE, HMODULE, HWND, RECT},
+        Foundation::{BOOL, HANDLE, HMODULE, HWND, POINT, RECT},
         Graphics::Gdi::{ClientToScreen, HMONITOR},
         System::{
             LibraryLoader::{GetProcAddress, LoadLibraryA},
@@ -26,17 +26,18 @@ use windows_sys::{
                 Pointer::{POINTER_INFO, POINTER_PEN_INFO, POINTER_TOUCH_INFO},
             },
             WindowsAndMessaging::{
-                ClipCursor, GetClientRect, GetClipCursor, GetSystemMetrics, GetWindowPlacement,
-                GetWindowRect, IsIconic, ShowCursor, IDC_APPSTARTING, IDC_ARROW, IDC_CROSS,
-                IDC_HAND, IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW, IDC_SIZENS,
-                IDC_SIZENWSE, IDC_SIZEWE, IDC_WAIT, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
-                SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SW_MAXIMIZE, WINDOWPLACEMENT,
+                ClipCursor, GetClientRect, GetClipCursor, GetCursorPos, GetSystemMetrics,
+                GetWindowPlacement, GetWindowRect, IsIconic, ShowCursor, IDC_APPSTARTING, IDC_ARROW,
+                IDC_CROSS, IDC_HAND, IDC_HELP, IDC_IBEAM, IDC_NO, IDC_SIZEALL, IDC_SIZENESW,
+                IDC_SIZENS, IDC_SIZENWSE, IDC_SIZEWE, IDC_WAIT, SM_CXVIRTUALSCREEN,
+                SM_CYVIRTUALSCREEN, SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SW_MAXIMIZE,
+                SYSTEM_METRICS_INDEX, WINDOWPLACEMENT,
             },
         },
     },
 };
 
-use crate::window::CursorIcon;
+use crate::{dpi::PhysicalPosition, window::CursorIcon};
 
 pub fn encode_wide(string: impl AsRef<OsStr>) -> Vec<u16> {
     string.as_ref().encode_wide().chain(once(0)).collect()
@@ -111,6 +112,16 @@ pub fn set_cursor_hidden(hidden: bool) {
     }
 }
 
+/// Returns the position of the cursor in desktop coordinates, or `None` if it can't be queried,
+/// e.g. while the desktop is locked.
+pub fn cursor_position() -> Option<PhysicalPosition<f64>> {
+    let mut point = POINT { x: 0, y: 0 };
+    if unsafe { GetCursorPos(&mut point) } == false.into() {
+        return None;
+    }
+    Some(PhysicalPosition::new(point.x as f64, point.y as f64))
+}
+
 pub fn get_cursor_clip() -> Result<RECT, io::Error> {
     unsafe {
         let mut rect: RECT = mem::zeroed();
@@ -234,6 +245,8 @@ pub type GetDpiForMonitor = unsafe extern "system" fn(
     dpi_y: *mut u32,
 ) -> HRESULT;
 pub type EnableNonClientDpiScaling = unsafe extern "system" fn(hwnd: HWND) -> BOOL;
+pub type GetSystemMetricsForDpi =
+    unsafe extern "system" fn(nIndex: SYSTEM_METRICS_INDEX, dpi: u32) -> i32;
 pub type AdjustWindowRectExForDpi = unsafe extern "system" fn(
     rect: *mut RECT,
     dwStyle: u32,
@@ -268,6 +281,8 @@ pub static ADJUST_WINDOW_RECT_EX_FOR_DPI: Lazy<Option<AdjustWindowRectExForDpi>>
     Lazy::new(|| get_function!("user32.dll", AdjustWindowRectExForDpi));
 pub static GET_DPI_FOR_MONITOR: Lazy<Option<GetDpiForMonitor>> =
     Lazy::new(|| get_function!("shcore.dll", GetDpiForMonitor));
+pub static GET_SYSTEM_METRICS_FOR_DPI: Lazy<Option<GetSystemMetricsForDpi>> =
+    Lazy::new(|| get_function!("user32.dll", GetSystemMetricsForDpi));
 pub static ENABLE_NON_CLIENT_DPI_SCALING: Lazy<Option<EnableNonClientDpiScaling>> =
     Lazy::new(|| get_function!("user32.dll", EnableNonClientDpiScaling));
 pub static SET_PROCESS_DPI_AWARENESS_CONTEXT: Lazy<Option<SetProcessDpiAwarenessContext>> =
@@ -981,6 +981,11 @@ impl Window {
         unsafe { set_skip_taskbar(self.hwnd(), skip) };
     }
 
+    #[inline]
+    pub fn set_hide_on_deactivate(&self, hide: bool) {
+        self.window_state_lock().hide_on_deactivate = hide;
+    }
+
     #[inline]
     pub fn set_undecorated_shadow(&self, shadow: bool) {
         let window = self.window;
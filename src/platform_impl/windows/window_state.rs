@@ -58,6 +58,9 @@ pub(crate) struct WindowState {
     pub dragging: bool,
 
     pub skip_taskbar: bool,
+
+    /// Used by `WM_ACTIVATE` to hide tray flyouts when they lose activation.
+    pub hide_on_deactivate: bool,
 }
 
 #[derive(Clone)]
@@ -174,6 +177,8 @@ impl WindowState {
             dragging: false,
 
             skip_taskbar: false,
+
+            hide_on_deactivate: false,
         }
     }
 
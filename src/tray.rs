@@ -1,11 +1,61 @@
+use std::{borrow::Cow, error::Error, fmt, sync::mpsc, time::Duration};
+
 use rwh_06::RawWindowHandle;
 
-use crate::{error::OsError, event_loop::EventLoopWindowTarget, platform_impl, window::WindowId};
+use crate::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    error::{EventLoopError, OsError},
+    event_loop::EventLoopWindowTarget,
+    platform_impl,
+    window::{BadIcon, Window, WindowBuilder, WindowId},
+};
+
+#[cfg(feature = "tray-menu")]
+pub use self::menu::{MenuId, MenuItemData, MenuRole, TrayMenu};
+#[cfg(feature = "tray-menu")]
+pub(crate) use self::menu::MenuEntry;
+#[cfg(feature = "tray-notifications")]
+pub use self::notification::{AlertOptions, NotificationBuilder, NotificationImage, SoundSource};
+pub use self::queue::TrayEventQueue;
+pub use self::standalone::StandaloneHandle;
+pub use self::status::{StateKey, StatusIconSet};
+#[cfg(feature = "tray-menu")]
+pub use self::status_tray::{StatusTray, StatusTrayEvent, StatusTrayEvents};
+
+#[cfg(feature = "tray-menu")]
+mod menu;
+#[cfg(feature = "tray-notifications")]
+mod notification;
+mod queue;
+mod standalone;
+mod status;
+#[cfg(feature = "tray-menu")]
+mod status_tray;
 
 pub struct TrayBuilder {
     pub(crate) icon: Option<crate::window::Icon>,
-    pub(crate) tooltip: Option<String>,
+    pub(crate) tooltip: Option<Cow<'static, str>>,
+    pub(crate) tooltip_fn: Option<Box<dyn Fn() -> String>>,
     pub(crate) parent_window: Option<RawWindowHandle>,
+    #[cfg(feature = "tray-menu")]
+    pub(crate) menu: Option<TrayMenu>,
+    pub(crate) require_icon: bool,
+    pub(crate) prefer_promoted: bool,
+    pub(crate) start_hidden: bool,
+    pub(crate) primary_action: Option<Box<dyn Fn()>>,
+    #[cfg(feature = "tray-menu")]
+    pub(crate) primary_item: Option<MenuId>,
+    #[cfg(feature = "tray-menu")]
+    pub(crate) menu_trigger: MenuTrigger,
+    pub(crate) window_kind: Option<WindowKind>,
+    pub(crate) hover_flyout: Option<(Duration, Box<dyn Fn() -> WindowBuilder>)>,
+    pub(crate) shell_retries: u32,
+    pub(crate) emit_cursor_moved: bool,
+    pub(crate) event_queue: Option<TrayEventQueue>,
+    pub(crate) event_sender: Option<mpsc::Sender<TrayEvent>>,
+    pub(crate) status_icons: Option<StatusIconSet>,
+    pub(crate) persist_badge: Option<String>,
+    pub(crate) platform_specific: platform_impl::PlatformSpecificTrayBuilderAttributes,
 }
 
 impl TrayBuilder {
@@ -13,7 +63,27 @@ impl TrayBuilder {
         TrayBuilder {
             icon: None,
             tooltip: None,
+            tooltip_fn: None,
             parent_window: None,
+            #[cfg(feature = "tray-menu")]
+            menu: None,
+            require_icon: false,
+            prefer_promoted: false,
+            start_hidden: false,
+            primary_action: None,
+            #[cfg(feature = "tray-menu")]
+            primary_item: None,
+            #[cfg(feature = "tray-menu")]
+            menu_trigger: MenuTrigger::RightClick,
+            window_kind: None,
+            hover_flyout: None,
+            shell_retries: 3,
+            emit_cursor_moved: false,
+            event_queue: None,
+            event_sender: None,
+            status_icons: None,
+            persist_badge: None,
+            platform_specific: Default::default(),
         }
     }
 
@@ -22,8 +92,40 @@ impl TrayBuilder {
         self
     }
 
-    pub fn with_tooltip(mut self, tooltip: &str) -> TrayBuilder {
-        self.tooltip = Some(tooltip.to_string());
+    /// Uses the small icon of `window` for the tray, keeping the previous icon if the window has
+    /// none.
+    ///
+    /// The icon is copied, so later changes to the icon of the window aren't reflected.
+    pub fn inherit_window_icon(mut self, window: &Window) -> TrayBuilder {
+        if let Some(icon) = platform_impl::Tray::window_icon(&window.window) {
+            self.icon = Some(icon);
+        }
+        self
+    }
+
+    /// Sets the tooltip of the icon.
+    ///
+    /// A static string is kept as is until the tray is built.
+    pub fn with_tooltip(mut self, tooltip: impl Into<Cow<'static, str>>) -> TrayBuilder {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Generates the tooltip with `tooltip` when the cursor hovers the icon, for tooltips that
+    /// are expensive to keep up to date. It replaces the tooltip given with
+    /// [`TrayBuilder::with_tooltip`] once called.
+    ///
+    /// Tooltips that are too long are truncated.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Called on the event loop thread when the cursor moves over the icon, at
+    ///   most once per second.
+    pub fn with_tooltip_fn<F>(mut self, tooltip: F) -> TrayBuilder
+    where
+        F: Fn() -> String + 'static,
+    {
+        self.tooltip_fn = Some(Box::new(tooltip));
         self
     }
 
@@ -32,29 +134,812 @@ impl TrayBuilder {
         self
     }
 
+    /// Sets the context menu shown when the icon is right-clicked.
+    #[cfg(feature = "tray-menu")]
+    pub fn with_menu(mut self, menu: TrayMenu) -> TrayBuilder {
+        self.menu = Some(menu);
+        self
+    }
+
+    /// Whether a missing icon is an error.
+    ///
+    /// When no icon is given through [`TrayBuilder::with_icon`], the `tray-default` icon resource
+    /// of the executable is used, falling back to the generic application icon if it can't be
+    /// loaded. Requiring an icon makes [`TrayBuilder::build`] fail with
+    /// [`TrayError::InvalidIcon`] instead of using that fallback.
+    ///
+    /// The default is `false`.
+    pub fn require_icon(mut self, require_icon: bool) -> TrayBuilder {
+        self.require_icon = require_icon;
+        self
+    }
+
+    /// Asks for the icon to be shown on the taskbar rather than hidden in the overflow area, the
+    /// first time the application runs.
+    ///
+    /// This is only a hint: the OS may ignore it, and it never overrides where the user chose to
+    /// put the icon.
+    ///
+    /// The default is `false`.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Makes sure the icon isn't hidden, and on Windows 11 marks it as promoted in
+    ///   the notification area settings unless the user changed them. Those settings only exist
+    ///   once Explorer has seen the icon, so this may only take effect on the next run.
+    pub fn prefer_promoted(mut self, prefer_promoted: bool) -> TrayBuilder {
+        self.prefer_promoted = prefer_promoted;
+        self
+    }
+
+    /// Creates the icon hidden, until [`Tray::reveal`] is called.
+    ///
+    /// Unlike building the tray only once it is needed, the icon is registered right away, so it
+    /// keeps its position among the other icons and its menu and notifications can be set up
+    /// beforehand. Revealing it doesn't flicker like adding an icon does.
+    ///
+    /// The default is `false`.
+    pub fn start_hidden(mut self, start_hidden: bool) -> TrayBuilder {
+        self.start_hidden = start_hidden;
+        self
+    }
+
+    /// Sets an action run on the event loop thread when the icon is clicked with the left mouse
+    /// button, in addition to the [`TrayEvent::LeftClicked`] event.
+    ///
+    /// This suits applications with a single main action.
+    #[cfg_attr(
+        feature = "tray-menu",
+        doc = " It is typically combined with [`MenuTrigger::RightClickOrLongPress`] to reach the",
+        doc = " menu without a right click."
+    )]
+    pub fn primary_action<F>(mut self, action: F) -> TrayBuilder
+    where
+        F: Fn() + 'static,
+    {
+        self.primary_action = Some(Box::new(action));
+        self
+    }
+
+    /// Makes clicking the icon with the left mouse button also choose the item `id` of the
+    /// [`TrayMenu`], emitting a [`TrayEvent::MenuItemClicked`] for it after the
+    /// [`TrayEvent::LeftClicked`] event, without the menu being opened.
+    ///
+    /// [`TrayBuilder::build`] fails with [`TrayError::UnknownMenuItem`] if the menu doesn't
+    /// contain the item outside of [lazy submenus](TrayMenu::lazy_submenu).
+    #[cfg(feature = "tray-menu")]
+    pub fn on_primary(mut self, id: MenuId) -> TrayBuilder {
+        self.primary_item = Some(id);
+        self
+    }
+
+    /// Sets how the user opens the [`TrayMenu`] with the mouse. The menu can always be opened
+    /// with the keyboard.
+    ///
+    /// The default is [`MenuTrigger::RightClick`].
+    #[cfg(feature = "tray-menu")]
+    pub fn menu_trigger(mut self, trigger: MenuTrigger) -> TrayBuilder {
+        self.menu_trigger = trigger;
+        self
+    }
+
+    /// Sets the kind of window backing the tray.
+    ///
+    /// The default is [`WindowKind::Owned`] with a parent window, and [`WindowKind::TopLevel`]
+    /// otherwise. See [`WindowKind::MessageOnly`] for what a message-only window gives up.
+    pub fn window_kind(mut self, kind: WindowKind) -> TrayBuilder {
+        self.window_kind = Some(kind);
+        self
+    }
+
+    /// Shows a window next to the icon once the cursor has hovered the icon for `delay`, and
+    /// destroys it when the cursor leaves, for status information richer than a tooltip.
+    ///
+    /// The window is built from the builder returned by `window` each time it is shown, so it
+    /// only exists while the cursor hovers the icon. It is shown without being activated, so the
+    /// application in the foreground keeps the focus. Its events are delivered to the event loop,
+    /// e.g. to redraw it.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Replaces the tooltip, and the delay adds up to the hover time of the shell.
+    pub fn hover_flyout<F>(mut self, delay: Duration, window: F) -> TrayBuilder
+    where
+        F: Fn() -> WindowBuilder + 'static,
+    {
+        self.hover_flyout = Some((delay, Box::new(window)));
+        self
+    }
+
+    /// Whether a [`WindowEvent::CursorMoved`] is emitted for the window of the tray before each
+    /// [`WindowEvent::MouseInput`], as trays used to do before having their own [`TrayEvent`]s.
+    ///
+    /// The default is `false`, as the position is also carried by the [`TrayEvent`]s.
+    ///
+    /// [`WindowEvent::CursorMoved`]: crate::event::WindowEvent::CursorMoved
+    /// [`WindowEvent::MouseInput`]: crate::event::WindowEvent::MouseInput
+    pub fn emit_cursor_moved(mut self, emit_cursor_moved: bool) -> TrayBuilder {
+        self.emit_cursor_moved = emit_cursor_moved;
+        self
+    }
+
+    /// Pushes the [`TrayEvent`]s of the tray into `queue` instead of sending them to the event
+    /// loop, so that they can be drained from a loop of the application.
+    ///
+    /// Events of the window of the tray, like those enabled by
+    /// [`TrayBuilder::emit_cursor_moved`], are still sent to the event loop.
+    pub fn with_event_queue(mut self, queue: TrayEventQueue) -> TrayBuilder {
+        self.event_queue = Some(queue);
+        self
+    }
+
+    /// Gives the tray icons to switch between with [`Tray::set_status`].
+    pub fn with_status_icons(mut self, status_icons: StatusIconSet) -> TrayBuilder {
+        self.status_icons = Some(status_icons);
+        self
+    }
+
+    /// Remembers the status set with [`Tray::set_status`] across runs, and shows the icon of the
+    /// last one from the start, so the icon doesn't change right after appearing.
+    ///
+    /// The status is remembered per executable under `name`, which tells the trays of an
+    /// application apart and so must stay the same across runs, e.g. `"main"`. Statuses saved by
+    /// executables that no longer exist, and statuses no longer in the set, are forgotten.
+    ///
+    /// By default, the status isn't remembered.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** The status is kept in the registry, under `HKEY_CURRENT_USER`.
+    pub fn persist_badge(mut self, name: &str) -> TrayBuilder {
+        self.persist_badge = Some(name.to_string());
+        self
+    }
+
+    /// Sets how many times a call to the shell is retried when it fails transiently, e.g. while
+    /// the shell restarts or is busy around login.
+    ///
+    /// Retries wait increasingly long, starting at 50 ms. The default is 3.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Applies to adding, modifying and removing the icon.
+    pub fn shell_retries(mut self, retries: u32) -> TrayBuilder {
+        self.shell_retries = retries;
+        self
+    }
+
+    /// Checks the whole configuration, returning every problem found instead of only the first.
+    ///
+    /// [`TrayBuilder::build`] performs the same checks, reporting several problems as
+    /// [`TrayError::Invalid`].
+    pub fn validate(&self) -> Result<(), Vec<TrayError>> {
+        let errors = platform_impl::Tray::validate(self);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     pub fn build<T: 'static>(
         self,
         window_target: &EventLoopWindowTarget<T>,
-    ) -> Result<Tray, OsError> {
+    ) -> Result<Tray, TrayError> {
+        if let Err(mut errors) = self.validate() {
+            return Err(if errors.len() == 1 {
+                errors.remove(0)
+            } else {
+                TrayError::Invalid(errors)
+            });
+        }
         let tray = platform_impl::Tray::new::<T>(self, &window_target.p).map(Tray)?;
 
         Ok(tray)
     }
+
+    /// Builds the tray like [`TrayBuilder::build`], sending its [`TrayEvent`]s to the returned
+    /// receiver instead of the event loop or the [queue](TrayBuilder::with_event_queue).
+    ///
+    /// The events pile up in the channel until they are received, so the receiver should be
+    /// drained regularly, e.g. on [`Event::AboutToWait`], or dropped once the events are no
+    /// longer wanted.
+    ///
+    /// [`Event::AboutToWait`]: crate::event::Event::AboutToWait
+    pub fn build_with_channel<T: 'static>(
+        mut self,
+        window_target: &EventLoopWindowTarget<T>,
+    ) -> Result<(Tray, mpsc::Receiver<TrayEvent>), TrayError> {
+        let (sender, receiver) = mpsc::channel();
+        self.event_sender = Some(sender);
+        self.build(window_target).map(|tray| (tray, receiver))
+    }
 }
 
-pub struct Tray(platform_impl::Tray);
+pub struct Tray(pub(crate) platform_impl::Tray);
 
 impl Tray {
+    /// Probes whether tray icons can be shown in the current session.
+    ///
+    /// This is meant to tell apart a tray that is disabled by the environment, e.g. by group
+    /// policy in locked-down installations, from a bug, when [`TrayBuilder::build`] fails.
+    pub fn diagnose() -> TrayDiagnostics {
+        platform_impl::Tray::diagnose()
+    }
 
-    pub fn id(&self) -> WindowId {
+    /// Creates a tray on a dedicated thread running its own event loop, for applications that
+    /// don't otherwise need one.
+    ///
+    /// `builder` is called on that thread, so the menu it contains doesn't need to be `Send`.
+    /// The returned handle owns the tray, which is used through [`StandaloneHandle::tray`]. The
+    /// events of the tray are received through [`StandaloneHandle::events`], and dropping the
+    /// handle removes the icon.
+    ///
+    /// Only one event loop can be created per process, so this fails with
+    /// [`EventLoopError::RecreationAttempt`] if the application already created one, and the
+    /// application can't create one afterwards.
+    pub fn spawn_standalone<F>(builder: F) -> Result<StandaloneHandle, TrayError>
+    where
+        F: FnOnce() -> TrayBuilder + Send + 'static,
+    {
+        standalone::spawn(builder)
+    }
+
+    /// Creates a tray with its own event loop on the current thread, for applications with a
+    /// foreign main loop that drives it with [`StandaloneHandle::pump_events`].
+    ///
+    /// The returned handle owns the tray, which is used through [`StandaloneHandle::tray`], and
+    /// dropping it removes the icon. As with [`Tray::spawn_standalone`], this fails if the
+    /// application already created an event loop.
+    pub fn standalone(builder: TrayBuilder) -> Result<StandaloneHandle, TrayError> {
+        standalone::create(builder)
+    }
+
+    /// Registers the current executable to start when the user logs in, or unregisters it.
+    ///
+    /// The executable is registered in the `Run` key of the current user under its file name, so
+    /// this doesn't require elevation. The registration points at the path of the executable, so
+    /// it must be renewed if the executable moves.
+    pub fn set_run_at_startup(enabled: bool) -> Result<(), OsError> {
+        platform_impl::Tray::set_run_at_startup(enabled)
+    }
+
+    /// Whether the current executable is registered to start when the user logs in.
+    ///
+    /// This is `false` if the registration points at another copy of the executable.
+    pub fn runs_at_startup() -> bool {
+        platform_impl::Tray::runs_at_startup()
+    }
+
+    /// The class of the windows created by trays, to pass to [`Tray::cleanup_orphans`].
+    pub const WINDOW_CLASS: &'static str = platform_impl::TRAY_WINDOW_CLASS;
+
+    /// Removes the icons left behind by previous runs that didn't remove them, e.g. because they
+    /// crashed, returning how many were removed.
+    ///
+    /// This is best-effort: it only knows about the icons created by trays of the current
+    /// executable whose window is of `window_class`, normally [`Tray::WINDOW_CLASS`], and icons
+    /// of live processes are left alone, so other applications' icons are never removed. The
+    /// shell also drops orphaned icons by itself when the cursor hovers them.
+    pub fn cleanup_orphans(window_class: &str) -> Result<usize, OsError> {
+        platform_impl::Tray::cleanup_orphans(window_class)
+    }
+
+    /// Returns an identifier unique to the tray icon, matching the `tray_id` of the
+    /// [`Event::TrayEvent`](crate::event::Event::TrayEvent)s it emits.
+    pub fn id(&self) -> TrayId {
         self.0.id()
     }
 
+    /// Returns the identifier of the hidden window backing the tray icon.
+    pub fn window_id(&self) -> WindowId {
+        self.0.window_id()
+    }
+
+    /// Changes the icon.
+    ///
+    /// From another thread than the one running the event loop, the change is sent to the event
+    /// loop thread like with [`TrayUpdater::set_icon`], and errors reported by the OS are logged
+    /// instead of returned. See [`Tray::set_icon_sync`] to apply it right away.
+    pub fn set_icon(&self, icon: crate::window::Icon) -> Result<(), OsError> {
+        self.0.set_icon(icon)
+    }
+
+    /// Changes the icon right away, failing with [`TrayError::WrongThread`] from another thread
+    /// than the one running the event loop.
+    pub fn set_icon_sync(&self, icon: crate::window::Icon) -> Result<(), TrayError> {
+        self.0.set_icon_sync(icon)
+    }
+
+    /// Changes the tooltip.
+    ///
+    /// From another thread than the one running the event loop, the change is sent to the event
+    /// loop thread like with [`TrayUpdater::set_tooltip`]. See [`Tray::set_tooltip_sync`] to
+    /// apply it right away.
+    pub fn set_tooltip(&self, tooltip: &str) -> Result<(), OsError> {
+        self.0.set_tooltip(tooltip)
+    }
+
+    /// Changes the tooltip right away, failing with [`TrayError::WrongThread`] from another
+    /// thread than the one running the event loop.
+    pub fn set_tooltip_sync(&self, tooltip: &str) -> Result<(), TrayError> {
+        self.0.set_tooltip_sync(tooltip)
+    }
+
+    /// Emits `event` from this icon as if it came from the OS.
+    ///
+    /// The event goes through the same path as real ones, so it is delivered to the event loop
+    /// once it gets to process the tray's messages. Only meant for tests: it requires the
+    /// `tray-mock` feature and is compiled out in release builds.
+    #[doc(hidden)]
+    #[cfg(all(feature = "tray-mock", debug_assertions))]
+    pub fn __inject_event(&self, event: TrayEvent) -> Result<(), OsError> {
+        self.0.inject_event(event)
+    }
+
+    /// Shows a notification next to the icon.
+    ///
+    /// A notification withheld because of [`NotificationBuilder::respect_quiet_time`] isn't an
+    /// error.
+    #[cfg(feature = "tray-notifications")]
+    pub fn show_notification(&self, notification: NotificationBuilder) -> Result<(), OsError> {
+        self.0.show_notification(notification, None)
+    }
+
+    /// Shows a notification next to the icon, calling `on_click` on the event loop thread if the
+    /// user clicks it.
+    ///
+    /// `on_click` is dropped without being called once the notification is dismissed or times
+    /// out, or if it is replaced by another one before being shown.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only supported by balloons. `on_click` is dropped right away for toasts.
+    #[cfg(feature = "tray-notifications")]
+    pub fn show_notification_with_callback<F>(
+        &self,
+        notification: NotificationBuilder,
+        on_click: F,
+    ) -> Result<(), OsError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.0.show_notification(notification, Some(Box::new(on_click)))
+    }
+
+    /// Shows a notification that must get the attention of the user right away, e.g. for
+    /// critical errors, along with the parent window requesting attention and a sound as set by
+    /// `options`.
+    ///
+    /// If the notification is withheld because of [`NotificationBuilder::respect_quiet_time`],
+    /// so are the request for attention and the sound.
+    #[cfg(feature = "tray-notifications")]
+    pub fn alert(
+        &self,
+        notification: NotificationBuilder,
+        options: AlertOptions,
+    ) -> Result<(), OsError> {
+        self.0.alert(notification, options)
+    }
+
+    /// Returns the number of notifications withheld during quiet time, see
+    /// [`NotificationBuilder::respect_quiet_time`].
+    #[cfg(feature = "tray-notifications")]
+    pub fn pending_quiet_notifications(&self) -> usize {
+        self.0.pending_quiet_notifications()
+    }
+
+    /// Shows the notifications withheld during quiet time, unless it is still going on, and
+    /// returns how many were shown.
+    ///
+    /// The notifications are kept if they fail to be shown.
+    #[cfg(feature = "tray-notifications")]
+    pub fn flush_quiet_notifications(&self) -> Result<usize, OsError> {
+        self.0.flush_quiet_notifications()
+    }
+
+    /// Tints the icon by multiplying each of its channels with the matching one of an RGBA color,
+    /// or restores the icon with `None`.
+    ///
+    /// This is meant for coloring a white icon to reflect a status, like green for success and red
+    /// for errors. Tinted icons are cached per color until the icon is changed, so switching
+    /// between a few colors is cheap. The tint also applies to icons set afterwards.
+    ///
+    /// Fails with [`TrayError::WrongThread`] from another thread than the one running the event
+    /// loop.
+    pub fn set_icon_tint(&self, tint: Option<[u8; 4]>) -> Result<(), TrayError> {
+        self.0.set_icon_tint(tint)
+    }
+
+    /// Shows an icon created with [`TrayBuilder::start_hidden`]. Does nothing if the icon is
+    /// already shown.
+    ///
+    /// Fails with [`TrayError::WrongThread`] from another thread than the one running the event
+    /// loop.
+    pub fn reveal(&self) -> Result<(), TrayError> {
+        self.0.reveal()
+    }
+
+    /// Shows the icon of the state `key` of the [`StatusIconSet`] given with
+    /// [`TrayBuilder::with_status_icons`].
+    ///
+    /// The icon was rendered along with the set, so this is as cheap as switching icons gets.
+    /// The status is shown until the icon or its tint are set again.
+    ///
+    /// Fails with [`TrayError::WrongThread`] from another thread than the one running the event
+    /// loop.
+    pub fn set_status(&self, key: StateKey) -> Result<(), TrayError> {
+        self.0.set_status(key)
+    }
+
+    /// Moves the progress bar of the notification tagged `tag` to `value`, between `0.0` and
+    /// `1.0`.
+    ///
+    /// See [`NotificationBuilder::with_progress`].
+    #[cfg(feature = "tray-notifications")]
+    pub fn update_notification_progress(&self, tag: &str, value: f64) -> Result<(), OsError> {
+        self.0.update_notification_progress(tag, value)
+    }
+
+    /// Whether the user allows the notifications of the application, which are otherwise
+    /// silently dropped, e.g. to show a hint in the application instead.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Toasts report the setting of their application. For balloons, only the
+    ///   setting disabling all notifications is checked, as the setting of the application isn't
+    ///   exposed.
+    #[cfg(feature = "tray-notifications")]
+    pub fn notifications_enabled(&self) -> NotificationSetting {
+        self.0.notifications_enabled()
+    }
+
+    /// Returns a handle to update the icon from other threads.
+    pub fn updater(&self) -> TrayUpdater {
+        TrayUpdater(self.0.updater())
+    }
+
+    /// Changes the label of the item `id` of the [`TrayMenu`], without rebuilding the menu.
+    ///
+    /// The label follows the same rules as when creating the item, so an ampersand marks an
+    /// access key. This is typically used to toggle the text of an item between states, or to
+    /// keep a [status item](TrayMenu::status_item) up to date.
+    ///
+    /// Returns [`TrayError::UnknownMenuItem`] if the menu doesn't contain the item, including
+    /// items of a [lazy submenu](TrayMenu::lazy_submenu) that was rebuilt since.
+    #[cfg(feature = "tray-menu")]
+    pub fn set_item_label(&self, id: MenuId, label: &str) -> Result<(), TrayError> {
+        self.0.set_item_label(id, label)
+    }
+
+    /// Estimates the size the [`TrayMenu`] is shown at, to position it or other UI next to the
+    /// icon. Returns `None` if the tray has no menu.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Computed from the system metrics and menu font, as the size is only known
+    ///   while the menu is shown. Lazy submenus don't count until they were first shown.
+    #[cfg(feature = "tray-menu")]
+    pub fn menu_size(&self) -> Option<PhysicalSize<u32>> {
+        self.0.menu_size()
+    }
+
+    /// Enables or disables showing the [`TrayMenu`] on right-click.
+    ///
+    /// While disabled, right-clicks are still reported through [`TrayEvent::RightClicked`].
+    /// Enabling it again shows the same menu as before.
+    #[cfg(feature = "tray-menu")]
+    pub fn set_menu_enabled(&self, enabled: bool) {
+        self.0.set_menu_enabled(enabled)
+    }
+
+    /// Returns the area covered by the icon on the desktop.
+    ///
+    /// Returns `None` if the icon isn't visible, e.g. when it is hidden in the overflow area.
+    pub fn get_rect(&self) -> Option<PhysicalRect> {
+        self.0.get_rect()
+    }
+
+    /// Shows `window` as a flyout of the icon.
+    ///
+    /// The window is moved next to the icon, against the taskbar, then shown and focused. It is
+    /// hidden again as soon as it loses focus, so it can be reused for the next flyout.
+    ///
+    /// The window is expected to be created without decorations and hidden, see
+    /// [`WindowBuilder::with_decorations`](crate::window::WindowBuilder::with_decorations) and
+    /// [`WindowBuilder::with_visible`](crate::window::WindowBuilder::with_visible).
+    pub fn anchor_window(&self, window: &Window) -> Result<(), OsError> {
+        self.0.anchor_window(&window.window)
+    }
+
+    /// Returns the pixel size the notification area draws the icon at.
+    ///
+    /// The size is derived from the DPI of the monitor hosting the icon, so rendering an RGBA icon
+    /// at this size avoids any scaling by the shell. The value is queried on every call and thus
+    /// follows DPI changes.
+    pub fn recommended_icon_size(&self) -> PhysicalSize<u32> {
+        self.0.recommended_icon_size()
+    }
+}
+
+/// A handle to update a [`Tray`] from any thread, obtained with [`Tray::updater`].
+///
+/// Only [`TrayUpdater::set_icon`] and [`TrayUpdater::set_tooltip`] are available. The updates
+/// are sent to the event loop thread and applied there in order, so errors reported by the OS
+/// are logged instead of returned. Once the [`Tray`] is dropped, updates fail with an
+/// [`OsError`].
+#[derive(Clone)]
+pub struct TrayUpdater(platform_impl::TrayUpdater);
+
+impl TrayUpdater {
+    /// Changes the icon, see [`Tray::set_icon`].
     pub fn set_icon(&self, icon: crate::window::Icon) -> Result<(), OsError> {
         self.0.set_icon(icon)
     }
 
+    /// Changes the tooltip, see [`Tray::set_tooltip`].
+    ///
+    /// A tooltip that is too long is rejected right away.
     pub fn set_tooltip(&self, tooltip: &str) -> Result<(), OsError> {
         self.0.set_tooltip(tooltip)
     }
 }
+
+/// The report of [`Tray::diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TrayDiagnostics {
+    /// Whether the notification area of the taskbar was found.
+    pub notification_area_found: bool,
+    /// Whether a policy hides all tray icons.
+    pub hidden_by_policy: bool,
+    /// The OS error code returned when adding and removing a hidden test icon, if it failed.
+    pub test_icon_error: Option<i32>,
+}
+
+impl TrayDiagnostics {
+    /// Whether tray icons are expected to work.
+    pub fn is_ok(&self) -> bool {
+        self.notification_area_found && !self.hidden_by_policy && self.test_icon_error.is_none()
+    }
+}
+
+/// Whether notifications are shown, as returned by [`Tray::notifications_enabled`].
+#[cfg(feature = "tray-notifications")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NotificationSetting {
+    Enabled,
+    /// The user or a policy disabled the notifications.
+    Disabled,
+    /// The setting couldn't be determined.
+    Unknown,
+}
+
+/// A rectangle on the desktop, in physical pixels.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicalRect {
+    pub position: PhysicalPosition<i32>,
+    pub size: PhysicalSize<u32>,
+}
+
+/// Identifier of a tray icon.
+///
+/// Unique within the process.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TrayId(pub(crate) u32);
+
+impl TrayId {
+    /// Returns the raw value of the identifier, which is the `uID` the icon is registered with
+    /// unless it was adopted from other code.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the tray backed by the window `window_id`, if it is still alive.
+    ///
+    /// This is a compatibility shim for code routing tray events by [`WindowId`], from before
+    /// trays had their own identifier. That trays are backed by windows isn't guaranteed, so new
+    /// code should use [`TrayId`] directly.
+    pub fn from_window_id(window_id: WindowId) -> Option<TrayId> {
+        platform_impl::tray_id_from_window(window_id.0)
+    }
+
+    /// Returns the identifier of the window backing the tray, if it is still alive.
+    ///
+    /// This is a compatibility shim, see [`TrayId::from_window_id`].
+    pub fn as_window_id(self) -> Option<WindowId> {
+        platform_impl::window_from_tray_id(self).map(WindowId)
+    }
+}
+
+impl From<TrayId> for u32 {
+    fn from(id: TrayId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for TrayId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// How the [`TrayMenu`] is opened with the mouse, see [`TrayBuilder::menu_trigger`].
+#[cfg(feature = "tray-menu")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MenuTrigger {
+    /// Clicking the icon with the right mouse button.
+    #[default]
+    RightClick,
+    /// Clicking the icon with the right mouse button, or holding the left one down.
+    ///
+    /// Releasing the left button after the menu opened isn't reported as a
+    /// [`TrayEvent::LeftClicked`].
+    RightClickOrLongPress,
+}
+
+/// The kind of window backing a [`Tray`], see [`TrayBuilder::window_kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowKind {
+    /// A window that only handles messages, which is the lightest.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** A message-only window receives no broadcasts, which disables:
+    ///   - reloading the icon at the size matching a new DPI or display configuration,
+    ///   - [`TrayEvent::IconMoved`], emitted when the taskbar changes,
+    ///   - [`TrayEvent::DisplayChanged`],
+    ///   - bringing the menu to the foreground, so it may not close when clicking elsewhere.
+    MessageOnly,
+    /// A window owned by the parent window, so that the menu comes to the foreground along with
+    /// the application. Without a parent window, this is the same as [`WindowKind::TopLevel`].
+    Owned,
+    /// A standalone top-level window, which is never shown.
+    TopLevel,
+}
+
+/// Where the icon of a [`Tray`] is shown, as chosen by the user in the settings of the taskbar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IconVisibility {
+    /// The icon is shown on the taskbar.
+    Visible,
+    /// The icon is hidden in the overflow area of the taskbar, only shown when the user opens it.
+    Hidden,
+}
+
+/// Describes an event from a [`Tray`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrayEvent {
+    /// The icon was clicked with the left mouse button.
+    LeftClicked {
+        /// The position of the cursor in desktop coordinates.
+        position: PhysicalPosition<f64>,
+        /// Whether the icon was clicked in the overflow area of the taskbar rather than on the
+        /// taskbar itself, `false` when it can't be told.
+        from_overflow: bool,
+    },
+
+    /// The icon was clicked with the right mouse button.
+    #[cfg_attr(
+        feature = "tray-menu",
+        doc = "",
+        doc = " The [`TrayMenu`] is shown after this event unless disabled with",
+        doc = " [`Tray::set_menu_enabled`]."
+    )]
+    RightClicked {
+        /// The position of the cursor in desktop coordinates.
+        position: PhysicalPosition<f64>,
+        /// See [`TrayEvent::LeftClicked::from_overflow`].
+        from_overflow: bool,
+    },
+
+    /// An item of the [`TrayMenu`] was clicked.
+    #[cfg(feature = "tray-menu")]
+    MenuItemClicked {
+        id: MenuId,
+        /// The data of the item, if it was created with [`TrayMenu::item_with_data`].
+        data: Option<MenuItemData>,
+    },
+    /// The icon moved on the desktop, e.g. because the taskbar moved or was resized, which
+    /// windows anchored to it should follow.
+    ///
+    /// This isn't reported while the icon is in the overflow area.
+    IconMoved(PhysicalRect),
+    /// The configuration of the displays changed, e.g. a monitor was added or removed or its
+    /// resolution changed. The icon was rendered again at the size suiting its display, and the
+    /// event carries its new rectangle if known.
+    DisplayChanged(Option<PhysicalRect>),
+    /// The user showed or hid the icon through the settings of the taskbar. Applications can use
+    /// it to point out where the icon went.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only reported by Windows 11, whose settings are watched for changes. The
+    ///   setting applies to all the icons of the executable.
+    VisibilityChanged(IconVisibility),
+    /// A button added with [`NotificationBuilder::action`] was pressed.
+    #[cfg(feature = "tray-notifications")]
+    NotificationAction {
+        /// The [tag](NotificationBuilder::tag) of the notification.
+        notification: Option<String>,
+        action_id: String,
+    },
+}
+
+/// An error that may be generated when creating or updating a [`Tray`].
+#[derive(Debug)]
+pub enum TrayError {
+    /// The requested icon could not be loaded.
+    InvalidIcon(BadIcon),
+    /// The OS cannot perform the operation.
+    Os(OsError),
+    /// The tray was built, synchronously updated, revealed, tinted or switched to a status on
+    /// another thread than the one running the event loop, which has to process the messages of
+    /// the tray.
+    ///
+    /// Building the tray on another thread is also a debug assertion.
+    WrongThread,
+    /// The menu of the tray doesn't contain the requested item.
+    #[cfg(feature = "tray-menu")]
+    UnknownMenuItem(MenuId),
+    /// An item of a raw menu has an identifier in the range reserved for the items of
+    /// [`TrayMenu`]s.
+    #[cfg(feature = "tray-menu")]
+    InvalidMenuItemId(u32),
+    /// The event loop of a [standalone](Tray::spawn_standalone) tray could not be created.
+    EventLoop(EventLoopError),
+    /// The tooltip is longer than the platform allows.
+    TooltipTooLong,
+    /// The parent window isn't a window of the platform.
+    InvalidParentWindow,
+    /// The builder has several problems, as returned by [`TrayBuilder::validate`].
+    Invalid(Vec<TrayError>),
+    /// The callback message of the tray isn't in the range free for applications.
+    InvalidCallbackMessage(u32),
+    /// The tray has no status icon for the requested state.
+    UnknownStatus(StateKey),
+    /// The shell kept failing to update the icon, even after the
+    /// [retries](TrayBuilder::shell_retries).
+    ShellCallFailed(OsError),
+}
+
+impl From<OsError> for TrayError {
+    fn from(value: OsError) -> Self {
+        Self::Os(value)
+    }
+}
+
+impl fmt::Display for TrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrayError::InvalidIcon(e) => write!(f, "Failed to load the tray icon: {e}"),
+            TrayError::Os(e) => e.fmt(f),
+            #[cfg(feature = "tray-menu")]
+            TrayError::UnknownMenuItem(id) => write!(f, "The tray menu has no item {id}"),
+            #[cfg(feature = "tray-menu")]
+            TrayError::InvalidMenuItemId(id) => {
+                write!(f, "The tray menu item identifier {id:#x} is reserved")
+            }
+            TrayError::UnknownStatus(key) => write!(f, "The tray has no status icon for {key}"),
+            TrayError::EventLoop(e) => write!(f, "Failed to create the tray event loop: {e}"),
+            TrayError::WrongThread => {
+                write!(f, "The tray must be used on the thread running its event loop")
+            }
+            TrayError::ShellCallFailed(e) => write!(f, "The shell failed to update the tray: {e}"),
+            TrayError::InvalidCallbackMessage(message) => {
+                write!(f, "The tray callback message {message:#x} is reserved")
+            }
+            TrayError::TooltipTooLong => write!(f, "The tray tooltip is too long"),
+            TrayError::InvalidParentWindow => write!(f, "The parent window of the tray is invalid"),
+            TrayError::Invalid(errors) => {
+                write!(f, "The tray is misconfigured:")?;
+                for error in errors {
+                    write!(f, "\n- {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for TrayError {}
@@ -0,0 +1,216 @@
+use std::{
+    any::Any,
+    fmt,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+/// Identifier of an item in a [`TrayMenu`].
+///
+/// Identifiers are unique within the process, so they can be matched against
+/// [`TrayEvent::MenuItemClicked`](super::TrayEvent::MenuItemClicked) without knowing which menu
+/// the item belongs to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MenuId(pub(crate) u32);
+
+impl MenuId {
+    /// The first identifier handed out. Lower ones are left to the items of raw menus, whose
+    /// command identifiers traditionally fit in 16 bits, and zero is used by Win32 to signal that
+    /// no item was selected.
+    pub(crate) const FIRST: u32 = 0x1_0000;
+
+    fn next() -> MenuId {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(MenuId::FIRST);
+        MenuId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Returns the raw value of the identifier, which is the command identifier of the item in
+    /// the native menu.
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl From<MenuId> for u32 {
+    fn from(id: MenuId) -> Self {
+        id.0
+    }
+}
+
+impl fmt::Display for MenuId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Data attached to an item with [`TrayMenu::item_with_data`], reported along with its clicks.
+#[derive(Clone)]
+pub struct MenuItemData(Arc<dyn Any + Send + Sync>);
+
+impl MenuItemData {
+    /// Returns the data if it is of type `D`.
+    pub fn downcast_ref<D: Any>(&self) -> Option<&D> {
+        self.0.downcast_ref()
+    }
+}
+
+impl fmt::Debug for MenuItemData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MenuItemData").finish_non_exhaustive()
+    }
+}
+
+/// Compares whether both are the data of the same item.
+impl PartialEq for MenuItemData {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The purpose of an item added with [`TrayMenu::role_item`].
+///
+/// Roles let the platform present common items the way its users expect. On Windows they are
+/// ordinary items with a default label.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MenuRole {
+    /// Exits the application.
+    Quit,
+    /// Shows information about the application.
+    About,
+    /// Opens the settings of the application.
+    Preferences,
+}
+
+impl MenuRole {
+    /// Returns the label used for the role when none is given.
+    pub fn default_label(self) -> &'static str {
+        match self {
+            MenuRole::Quit => "&Quit",
+            MenuRole::About => "&About",
+            MenuRole::Preferences => "&Preferences",
+        }
+    }
+}
+
+/// The context menu shown when the tray icon is right-clicked.
+///
+/// Labels are passed to the OS as-is, so an ampersand marks the following character as the
+/// item's access key. Use `&&` for a literal ampersand.
+#[derive(Default)]
+pub struct TrayMenu {
+    pub(crate) entries: Vec<MenuEntry>,
+}
+
+pub(crate) enum MenuEntry {
+    Item {
+        id: MenuId,
+        label: String,
+        data: Option<MenuItemData>,
+    },
+    Status {
+        id: MenuId,
+        label: String,
+    },
+    Separator,
+    Submenu {
+        label: String,
+        menu: TrayMenu,
+    },
+    LazySubmenu {
+        label: String,
+        builder: Box<dyn Fn() -> TrayMenu>,
+    },
+}
+
+impl TrayMenu {
+    pub fn new() -> TrayMenu {
+        Default::default()
+    }
+
+    /// Appends a clickable item and returns its identifier.
+    pub fn item(&mut self, label: &str) -> MenuId {
+        let id = MenuId::next();
+        self.entries.push(MenuEntry::Item {
+            id,
+            label: label.to_string(),
+            data: None,
+        });
+        id
+    }
+
+    /// Appends a clickable item carrying `data`, and returns its identifier.
+    ///
+    /// The data is reported by [`TrayEvent::MenuItemClicked`](super::TrayEvent::MenuItemClicked)
+    /// when the item is clicked, which saves looking it up by identifier, e.g. for a list of
+    /// recent files in a [lazy submenu](TrayMenu::lazy_submenu).
+    pub fn item_with_data<D>(&mut self, label: &str, data: D) -> MenuId
+    where
+        D: Any + Send + Sync,
+    {
+        let id = MenuId::next();
+        self.entries.push(MenuEntry::Item {
+            id,
+            label: label.to_string(),
+            data: Some(MenuItemData(Arc::new(data))),
+        });
+        id
+    }
+
+    /// Appends an item for `role` with its [default label](MenuRole::default_label), and returns
+    /// its identifier.
+    pub fn role_item(&mut self, role: MenuRole) -> MenuId {
+        self.role_item_with_label(role, role.default_label())
+    }
+
+    /// Appends an item for `role` labelled `label`, and returns its identifier.
+    ///
+    /// Windows has no native roles, so this is the same as [`TrayMenu::item`] there.
+    pub fn role_item_with_label(&mut self, role: MenuRole, label: &str) -> MenuId {
+        let _ = role;
+        self.item(label)
+    }
+
+    /// Appends an item that can't be clicked, meant to show the status of the application.
+    ///
+    /// Its label can be updated with [`Tray::set_item_label`](super::Tray::set_item_label) using
+    /// the returned identifier.
+    pub fn status_item(&mut self, initial: &str) -> MenuId {
+        let id = MenuId::next();
+        self.entries.push(MenuEntry::Status {
+            id,
+            label: initial.to_string(),
+        });
+        id
+    }
+
+    /// Appends a separator line.
+    pub fn separator(&mut self) {
+        self.entries.push(MenuEntry::Separator);
+    }
+
+    /// Appends a submenu.
+    pub fn submenu(&mut self, label: &str, menu: TrayMenu) {
+        self.entries.push(MenuEntry::Submenu {
+            label: label.to_string(),
+            menu,
+        });
+    }
+
+    /// Appends a submenu whose content is produced by `builder`.
+    ///
+    /// The builder is invoked every time the submenu is about to be shown, which avoids building
+    /// large menus up front and keeps their content up to date. Clicks on the produced items are
+    /// reported like any other item.
+    pub fn lazy_submenu<F>(&mut self, label: &str, builder: F)
+    where
+        F: Fn() -> TrayMenu + 'static,
+    {
+        self.entries.push(MenuEntry::LazySubmenu {
+            label: label.to_string(),
+            builder: Box::new(builder),
+        });
+    }
+}
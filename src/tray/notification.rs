@@ -0,0 +1,295 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::window::{Icon, UserAttentionType};
+
+/// The number of characters the attribution of a notification is truncated to.
+const MAX_ATTRIBUTION_LEN: usize = 64;
+
+/// A notification shown next to a tray icon with [`Tray::show_notification`].
+///
+/// ## Platform-specific
+///
+/// - **Windows:** Shown as a balloon, or as a toast when the `tray-toast` feature is enabled and
+///   an AppUserModelID was given with `TrayBuilderExtWindows::with_app_user_model_id`.
+///
+/// [`Tray::show_notification`]: super::Tray::show_notification
+#[derive(Debug, Clone, Default)]
+pub struct NotificationBuilder {
+    pub(crate) title: String,
+    pub(crate) body: String,
+    pub(crate) attribution: Option<String>,
+    pub(crate) tag: Option<String>,
+    pub(crate) group: Option<String>,
+    pub(crate) progress: Option<(String, f64)>,
+    pub(crate) respect_quiet_time: bool,
+    pub(crate) icon: Option<Icon>,
+    /// The id and label of each action button.
+    pub(crate) actions: Vec<(String, String)>,
+    pub(crate) expires_after: Option<Duration>,
+    pub(crate) image: Option<NotificationImage>,
+    pub(crate) hero_image: Option<NotificationImage>,
+    pub(crate) sound: Option<SoundSource>,
+    pub(crate) silent: bool,
+}
+
+/// An image shown in a notification, see [`NotificationBuilder::with_image`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationImage {
+    /// A PNG, JPEG or GIF file.
+    Path(PathBuf),
+    /// The content of a PNG, JPEG or GIF file.
+    Bytes(Vec<u8>),
+}
+
+impl From<PathBuf> for NotificationImage {
+    fn from(path: PathBuf) -> NotificationImage {
+        NotificationImage::Path(path)
+    }
+}
+
+impl From<&std::path::Path> for NotificationImage {
+    fn from(path: &std::path::Path) -> NotificationImage {
+        NotificationImage::Path(path.to_path_buf())
+    }
+}
+
+impl From<Vec<u8>> for NotificationImage {
+    fn from(bytes: Vec<u8>) -> NotificationImage {
+        NotificationImage::Bytes(bytes)
+    }
+}
+
+/// A sound played along with a notification, see [`NotificationBuilder::with_sound`] and
+/// [`AlertOptions::with_sound`].
+///
+/// The default sound of the system is played instead if the sound can't be found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SoundSource {
+    /// A sound of the system, identified by its name.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Balloons play the sound registered under that name, e.g. `SystemAsterisk`,
+    ///   while toasts play the toast sound of that name, e.g. `Notification.Mail`.
+    Alias(String),
+    /// A sound file.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** A WAV file.
+    Path(PathBuf),
+}
+
+impl From<PathBuf> for SoundSource {
+    fn from(path: PathBuf) -> SoundSource {
+        SoundSource::Path(path)
+    }
+}
+
+impl From<&std::path::Path> for SoundSource {
+    fn from(path: &std::path::Path) -> SoundSource {
+        SoundSource::Path(path.to_path_buf())
+    }
+}
+
+impl NotificationBuilder {
+    pub fn new() -> NotificationBuilder {
+        Default::default()
+    }
+
+    pub fn with_title(mut self, title: &str) -> NotificationBuilder {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_body(mut self, body: &str) -> NotificationBuilder {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Sets the source of the notification, shown in smaller text below its body, e.g. the name
+    /// of a service the notification comes from. Text longer than 64 characters is truncated.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only shown by toasts, from Windows 10 version 1607. Balloons ignore the
+    ///   attribution.
+    pub fn attribution(mut self, attribution: &str) -> NotificationBuilder {
+        self.attribution = Some(attribution.chars().take(MAX_ATTRIBUTION_LEN).collect());
+        self
+    }
+
+    /// Sets the icon shown in the notification, which can be more detailed than the icon of the
+    /// tray. The icon of the tray is left unchanged either way.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only shown by balloons, at the size of large icons, e.g. 32x32 at 100%
+    ///   scaling. Without an icon, balloons have none.
+    pub fn with_icon(mut self, icon: Icon) -> NotificationBuilder {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Shows `image` inline, below the text of the notification.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only shown by toasts. Bytes are written to a temporary file, which is
+    ///   removed once the toast is dismissed. Balloons ignore the image.
+    pub fn with_image(mut self, image: impl Into<NotificationImage>) -> NotificationBuilder {
+        self.image = Some(image.into());
+        self
+    }
+
+    /// Shows `image` as a banner across the top of the notification.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** See [`NotificationBuilder::with_image`].
+    pub fn with_hero_image(mut self, image: impl Into<NotificationImage>) -> NotificationBuilder {
+        self.hero_image = Some(image.into());
+        self
+    }
+
+    /// Plays `sound` instead of the default sound when the notification is shown. The default
+    /// sound is played if `sound` can't be found.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Toasts of applications that aren't packaged only play the toast sounds of
+    ///   the system, given as [`SoundSource::Alias`].
+    pub fn with_sound(mut self, sound: impl Into<SoundSource>) -> NotificationBuilder {
+        self.sound = Some(sound.into());
+        self
+    }
+
+    /// Whether the notification is shown without playing any sound, even one given with
+    /// [`NotificationBuilder::with_sound`].
+    ///
+    /// The default is `false`.
+    pub fn silent(mut self, silent: bool) -> NotificationBuilder {
+        self.silent = silent;
+        self
+    }
+
+    /// Adds a button labelled `label` to the notification, emitting a
+    /// [`TrayEvent::NotificationAction`](super::TrayEvent::NotificationAction) with `id` when
+    /// pressed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only shown by toasts, which have at most 5 buttons. The event is only
+    ///   emitted while the application runs. Balloons ignore the actions.
+    pub fn action(mut self, id: &str, label: &str) -> NotificationBuilder {
+        self.actions.push((id.to_string(), label.to_string()));
+        self
+    }
+
+    /// Removes the notification from the notification center once `duration` has elapsed, so
+    /// outdated notifications don't pile up there.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only used by toasts, which otherwise stay in the Action Center for up to 3
+    ///   days. Balloons aren't kept after being dismissed.
+    pub fn expires_after(mut self, duration: Duration) -> NotificationBuilder {
+        self.expires_after = Some(duration);
+        self
+    }
+
+    /// Makes the notification replace the previous one of the same tray with the same tag
+    /// instead of being stacked with it, which suits progress-style updates.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only used by toasts, and limited to 64 characters. A balloon always
+    ///   replaces the previous balloon of its icon.
+    pub fn tag(mut self, tag: &str) -> NotificationBuilder {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Collapses the notification with the others of the same `group` under a header titled
+    /// `group` in the notification center. Unlike a [tag](NotificationBuilder::tag), it doesn't
+    /// replace other notifications.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only used by toasts, from Windows 10 version 1709. Balloons ignore the
+    ///   group.
+    pub fn group(mut self, group: &str) -> NotificationBuilder {
+        self.group = Some(group.to_string());
+        self
+    }
+
+    /// Whether to withhold the notification while the user doesn't want to be disturbed, e.g.
+    /// during quiet hours, a presentation or when a full-screen application is running.
+    ///
+    /// Withheld notifications can be delivered later with
+    /// [`Tray::flush_quiet_notifications`](super::Tray::flush_quiet_notifications).
+    ///
+    /// The default is `false`.
+    pub fn respect_quiet_time(mut self, respect_quiet_time: bool) -> NotificationBuilder {
+        self.respect_quiet_time = respect_quiet_time;
+        self
+    }
+
+    /// Adds a progress bar described by `label`, filled up to `value`, between `0.0` and `1.0`.
+    ///
+    /// The progress can be updated while the notification is shown with
+    /// [`Tray::update_notification_progress`](super::Tray::update_notification_progress) if the
+    /// notification has a [tag](NotificationBuilder::tag).
+    ///
+    /// ## Platform-specific
+    ///
+    /// - **Windows:** Only shown by toasts. Balloons ignore the progress.
+    pub fn with_progress(mut self, label: &str, value: f64) -> NotificationBuilder {
+        self.progress = Some((label.to_string(), value.clamp(0.0, 1.0)));
+        self
+    }
+}
+
+/// How [`Tray::alert`] draws the attention of the user besides showing the notification.
+///
+/// [`Tray::alert`]: super::Tray::alert
+#[derive(Debug, Clone)]
+pub struct AlertOptions {
+    pub(crate) attention: Option<UserAttentionType>,
+    pub(crate) sound: Option<SoundSource>,
+}
+
+impl Default for AlertOptions {
+    fn default() -> Self {
+        AlertOptions {
+            attention: Some(UserAttentionType::Critical),
+            sound: None,
+        }
+    }
+}
+
+impl AlertOptions {
+    pub fn new() -> AlertOptions {
+        Default::default()
+    }
+
+    /// Sets how the parent window of the tray requests the attention of the user, like
+    /// [`Window::request_user_attention`], or leaves it alone with `None`.
+    ///
+    /// The default is [`UserAttentionType::Critical`]. Trays without a
+    /// [parent window](super::TrayBuilder::parent_window) ignore it.
+    ///
+    /// [`Window::request_user_attention`]: crate::window::Window::request_user_attention
+    pub fn with_attention(mut self, attention: Option<UserAttentionType>) -> AlertOptions {
+        self.attention = attention;
+        self
+    }
+
+    /// Plays `sound` along with the notification, even if the notification is
+    /// [silent](NotificationBuilder::silent). The default sound is played if `sound` can't be
+    /// found.
+    pub fn with_sound(mut self, sound: impl Into<SoundSource>) -> AlertOptions {
+        self.sound = Some(sound.into());
+        self
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use super::{TrayEvent, TrayId};
+
+/// A queue collecting the [`TrayEvent`]s of trays, for applications driving their own loop
+/// rather than handling events in the event loop callback.
+///
+/// Trays built with [`TrayBuilder::with_event_queue`](super::TrayBuilder::with_event_queue)
+/// push their events here instead of sending them to the event loop. The event loop must still
+/// be pumped for the events to be received, e.g. with
+/// [`EventLoopExtPumpEvents::pump_events`](crate::platform::pump_events::EventLoopExtPumpEvents::pump_events).
+///
+/// Events are kept in the order they happened, across all the trays sharing the queue, along
+/// with the [`TrayId`] of the tray they happened to. Once the queue holds `capacity` events, the
+/// oldest one is dropped for each new event.
+///
+/// Clones share the same queue.
+#[derive(Debug, Clone)]
+pub struct TrayEventQueue {
+    events: Arc<Mutex<VecDeque<(TrayId, TrayEvent)>>>,
+    capacity: usize,
+}
+
+impl TrayEventQueue {
+    /// Creates a queue holding at most `capacity` events, which must be at least 1.
+    pub fn new(capacity: usize) -> TrayEventQueue {
+        assert!(capacity > 0, "A tray event queue must hold at least one event");
+        TrayEventQueue {
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Removes and returns the events received since the last call along with their tray,
+    /// oldest first.
+    pub fn drain(&self) -> Vec<(TrayId, TrayEvent)> {
+        self.events.lock().unwrap().drain(..).collect()
+    }
+
+    /// Returns the number of events waiting to be drained.
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn push(&self, tray_id: TrayId, event: TrayEvent) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back((tray_id, event));
+    }
+}
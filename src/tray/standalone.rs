@@ -0,0 +1,193 @@
+use std::{
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    event::Event,
+    event_loop::{EventLoop, EventLoopBuilder, EventLoopProxy},
+    platform::{pump_events::EventLoopExtPumpEvents, windows::EventLoopBuilderExtWindows},
+};
+
+use super::{Tray, TrayBuilder, TrayError, TrayEvent};
+
+/// Runs the message pump of a tray created with [`Tray::spawn_standalone`] or
+/// [`Tray::standalone`].
+///
+/// The handle owns the tray, which it lends with [`StandaloneHandle::tray`]. Dropping the
+/// handle removes the icon and stops the pump.
+pub struct StandaloneHandle {
+    /// Only taken when stopping.
+    tray: Option<Tray>,
+    events: Receiver<TrayEvent>,
+    pump: Pump,
+}
+
+enum Pump {
+    Thread {
+        proxy: EventLoopProxy<()>,
+        thread: Option<JoinHandle<()>>,
+    },
+    /// Pumped by the caller with [`StandaloneHandle::pump_events`].
+    Local { event_loop: EventLoop<()> },
+}
+
+impl StandaloneHandle {
+    /// Returns the tray, e.g. to update its icon.
+    pub fn tray(&self) -> &Tray {
+        self.tray.as_ref().unwrap()
+    }
+
+    /// Returns the receiver of the events of a tray created with [`Tray::spawn_standalone`].
+    ///
+    /// The channel is disconnected once the pump stops. It stays empty for trays created with
+    /// [`Tray::standalone`], whose events are returned by [`StandaloneHandle::pump_events`].
+    pub fn events(&self) -> &Receiver<TrayEvent> {
+        &self.events
+    }
+
+    /// Processes the pending messages of a tray created with [`Tray::standalone`], returning the
+    /// events they produced. This lets a tray be driven by a foreign main loop, on the thread
+    /// that created it.
+    ///
+    /// Like [`EventLoopExtPumpEvents::pump_events`], this waits up to `timeout` for a message
+    /// when none are pending, or indefinitely if `timeout` is `None`, so a zero timeout never
+    /// blocks.
+    ///
+    /// For a tray created with [`Tray::spawn_standalone`], this returns the events received
+    /// from its thread instead, waiting up to `timeout` for the first one.
+    ///
+    /// ## Reentrancy
+    ///
+    /// This must not be called while the tray is already processing messages, e.g. from a
+    /// [primary action](super::TrayBuilder::primary_action) or from a window procedure
+    /// dispatched by this call, as the event loop can't be nested.
+    pub fn pump_events(&mut self, timeout: Option<Duration>) -> Vec<TrayEvent> {
+        match &mut self.pump {
+            Pump::Local { event_loop, .. } => {
+                let mut events = Vec::new();
+                event_loop.pump_events(timeout, |event, _| {
+                    if let Event::TrayEvent { event, .. } = event {
+                        events.push(event);
+                    }
+                });
+                events
+            }
+            Pump::Thread { .. } => {
+                let first = match timeout {
+                    Some(timeout) => self.events.recv_timeout(timeout),
+                    None => self.events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                };
+                first.into_iter().chain(self.events.try_iter()).collect()
+            }
+        }
+    }
+
+    /// Removes the icon, then waits for the pump to stop.
+    pub fn stop(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        match &mut self.pump {
+            Pump::Thread { proxy, thread } => {
+                // Posts the destruction of the window, which the pump processes before stopping
+                // as the messages of the thread are processed in order.
+                drop(self.tray.take());
+                // Fails if the pump already stopped.
+                let _ = proxy.send_event(());
+                if let Some(thread) = thread.take() {
+                    let _ = thread.join();
+                }
+            }
+            Pump::Local { event_loop } => {
+                // Posts the destruction of the window, which the pump then processes.
+                if self.tray.take().is_some() {
+                    event_loop.pump_events(Some(Duration::ZERO), |_, _| ());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for StandaloneHandle {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+pub(super) fn create(builder: TrayBuilder) -> Result<StandaloneHandle, TrayError> {
+    let event_loop = EventLoopBuilder::new()
+        .with_any_thread(true)
+        .build()
+        .map_err(TrayError::EventLoop)?;
+    let tray = builder.build(&event_loop)?;
+    // Never sent to, the events being returned by `pump_events` instead.
+    let (_, events) = mpsc::channel();
+    Ok(StandaloneHandle {
+        tray: Some(tray),
+        events,
+        pump: Pump::Local { event_loop },
+    })
+}
+
+pub(super) fn spawn<F>(builder: F) -> Result<StandaloneHandle, TrayError>
+where
+    F: FnOnce() -> TrayBuilder + Send + 'static,
+{
+    let (init_sender, init_receiver) = mpsc::channel();
+    let (event_sender, events) = mpsc::channel();
+
+    let thread = thread::Builder::new()
+        .name("winit tray".into())
+        .spawn(move || {
+            let event_loop = match EventLoopBuilder::new().with_any_thread(true).build() {
+                Ok(event_loop) => event_loop,
+                Err(err) => {
+                    let _ = init_sender.send(Err(TrayError::EventLoop(err)));
+                    return;
+                }
+            };
+            match builder().build(&event_loop) {
+                Ok(tray) => {
+                    let proxy = event_loop.create_proxy();
+                    let _ = init_sender.send(Ok((tray, proxy)));
+                }
+                Err(err) => {
+                    let _ = init_sender.send(Err(err));
+                    return;
+                }
+            }
+
+            // The handle removes the icon before asking the loop to stop.
+            let _ = event_loop.run(move |event, elwt| match event {
+                Event::TrayEvent { event, .. } => {
+                    let _ = event_sender.send(event);
+                }
+                Event::UserEvent(()) => elwt.exit(),
+                _ => (),
+            });
+        })
+        .map_err(|err| TrayError::Os(os_error!(err)))?;
+
+    match init_receiver.recv() {
+        Ok(Ok((tray, proxy))) => Ok(StandaloneHandle {
+            tray: Some(tray),
+            events,
+            pump: Pump::Thread {
+                proxy,
+                thread: Some(thread),
+            },
+        }),
+        Ok(Err(err)) => {
+            let _ = thread.join();
+            Err(err)
+        }
+        // The builder panicked.
+        Err(_) => match thread.join() {
+            Err(panic) => std::panic::resume_unwind(panic),
+            Ok(()) => unreachable!("the tray thread exited without reporting"),
+        },
+    }
+}
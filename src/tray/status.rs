@@ -0,0 +1,61 @@
+use std::fmt;
+
+use crate::{
+    platform_impl,
+    window::{BadIcon, Icon},
+};
+
+/// Identifier of a state of a [`StatusIconSet`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StateKey(pub &'static str);
+
+impl fmt::Display for StateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Icons reflecting a fixed set of states, rendered once so that a tray can switch between them
+/// with [`Tray::set_status`](super::Tray::set_status) without rendering anything.
+///
+/// The set is given to a tray with
+/// [`TrayBuilder::with_status_icons`](super::TrayBuilder::with_status_icons), which keeps the
+/// icons for its whole lifetime.
+#[derive(Debug, Clone)]
+pub struct StatusIconSet {
+    pub(crate) icons: Vec<(StateKey, Icon)>,
+}
+
+impl StatusIconSet {
+    /// Renders a variant of `base` for each state, tinted with its RGBA color the same way as
+    /// [`Tray::set_icon_tint`](super::Tray::set_icon_tint).
+    ///
+    /// A state listed twice uses its last color.
+    pub fn generate(base: Icon, states: &[(StateKey, [u8; 4])]) -> Result<StatusIconSet, BadIcon> {
+        let mut icons: Vec<(StateKey, Icon)> = Vec::with_capacity(states.len());
+        for &(key, color) in states {
+            let icon = platform_impl::Tray::tinted_icon(&base, color)?;
+            match icons.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, existing)) => *existing = icon,
+                None => icons.push((key, icon)),
+            }
+        }
+        Ok(StatusIconSet { icons })
+    }
+
+    /// Returns the key of the state named `name`.
+    pub(crate) fn key(&self, name: &str) -> Option<StateKey> {
+        self.icons
+            .iter()
+            .map(|(key, _)| *key)
+            .find(|key| key.0 == name)
+    }
+
+    /// Returns the icon of the state `key`.
+    pub fn get(&self, key: StateKey) -> Option<&Icon> {
+        self.icons
+            .iter()
+            .find(|(existing, _)| *existing == key)
+            .map(|(_, icon)| icon)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    borrow::Cow,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+use super::{MenuId, MenuRole, Tray, TrayBuilder, TrayError, TrayEvent, TrayMenu};
+use crate::{event_loop::EventLoopWindowTarget, window::Icon};
+
+/// A tray set up for the common case: an icon with a tooltip, a context menu ending with a quit
+/// item, and an action run when the icon is clicked.
+///
+/// The menu opens on right-click and the action runs on left-click. Anything else is left to
+/// [`TrayBuilder`].
+pub struct StatusTray {
+    builder: TrayBuilder,
+    menu: TrayMenu,
+    quit_label: Option<String>,
+}
+
+impl StatusTray {
+    pub fn new(icon: Icon) -> StatusTray {
+        StatusTray {
+            builder: TrayBuilder::new().with_icon(icon),
+            menu: TrayMenu::new(),
+            quit_label: None,
+        }
+    }
+
+    /// Sets the tooltip of the icon, like [`TrayBuilder::with_tooltip`].
+    pub fn tooltip(mut self, tooltip: impl Into<Cow<'static, str>>) -> StatusTray {
+        self.builder = self.builder.with_tooltip(tooltip);
+        self
+    }
+
+    /// Sets the items of the context menu, followed by a separator and the quit item.
+    ///
+    /// Clicking the quit item is only reported as [`StatusTrayEvent::Quit`], leaving the
+    /// application to exit however it needs to, e.g. with [`EventLoopWindowTarget::exit`].
+    pub fn menu(mut self, menu: TrayMenu) -> StatusTray {
+        self.menu = menu;
+        self
+    }
+
+    /// Sets the label of the quit item instead of the [default one](MenuRole::default_label).
+    pub fn quit_label(mut self, label: &str) -> StatusTray {
+        self.quit_label = Some(label.to_string());
+        self
+    }
+
+    /// Runs `action` on the event loop thread when the icon is clicked with the left mouse
+    /// button, typically to show the main window.
+    pub fn on_activate<F>(mut self, action: F) -> StatusTray
+    where
+        F: Fn() + 'static,
+    {
+        self.builder = self.builder.primary_action(action);
+        self
+    }
+
+    /// Builds the tray, whose events are received from the returned [`StatusTrayEvents`].
+    pub fn build<T: 'static>(
+        self,
+        window_target: &EventLoopWindowTarget<T>,
+    ) -> Result<(Tray, StatusTrayEvents), TrayError> {
+        let mut menu = self.menu;
+        if !menu.entries.is_empty() {
+            menu.separator();
+        }
+        let quit_id = match &self.quit_label {
+            Some(label) => menu.role_item_with_label(MenuRole::Quit, label),
+            None => menu.role_item(MenuRole::Quit),
+        };
+
+        let (tray, receiver) = self
+            .builder
+            .with_menu(menu)
+            .build_with_channel(window_target)?;
+        Ok((tray, StatusTrayEvents { receiver, quit_id }))
+    }
+}
+
+/// An event of a [`StatusTray`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusTrayEvent {
+    /// The quit item of the menu was clicked, which doesn't exit the application by itself.
+    Quit,
+    /// Any other event of the tray.
+    Tray(TrayEvent),
+}
+
+/// Receives the events of a [`StatusTray`].
+///
+/// The events pile up until they are received, so they should be drained regularly, e.g. on
+/// [`Event::AboutToWait`](crate::event::Event::AboutToWait).
+#[derive(Debug)]
+pub struct StatusTrayEvents {
+    receiver: Receiver<TrayEvent>,
+    quit_id: MenuId,
+}
+
+impl StatusTrayEvents {
+    /// Returns the oldest event not received yet.
+    ///
+    /// Fails with [`TryRecvError::Empty`] if there is none, or [`TryRecvError::Disconnected`] once
+    /// the tray was dropped and all its events were received.
+    pub fn try_recv(&self) -> Result<StatusTrayEvent, TryRecvError> {
+        Ok(match self.receiver.try_recv()? {
+            TrayEvent::MenuItemClicked { id, .. } if id == self.quit_id => StatusTrayEvent::Quit,
+            event => StatusTrayEvent::Tray(event),
+        })
+    }
+
+    /// Returns the identifier of the quit item.
+    pub fn quit_id(&self) -> MenuId {
+        self.quit_id
+    }
+}
@@ -28,3 +28,10 @@ fn ids_send() {
     needs_send::<winit::event::DeviceId>();
     needs_send::<winit::monitor::MonitorHandle>();
 }
+
+#[test]
+#[cfg(target_os = "windows")]
+fn tray_updater_send() {
+    // ensures that `winit::tray::TrayUpdater` implements `Send`
+    needs_send::<winit::tray::TrayUpdater>();
+}
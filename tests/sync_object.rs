@@ -11,3 +11,10 @@ fn window_sync() {
 fn window_builder_sync() {
     needs_sync::<winit::window::WindowBuilder>();
 }
+
+#[test]
+#[cfg(target_os = "windows")]
+fn tray_updater_sync() {
+    // ensures that `winit::tray::TrayUpdater` implements `Sync`
+    needs_sync::<winit::tray::TrayUpdater>();
+}
@@ -0,0 +1,22 @@
+#![cfg(target_os = "windows")]
+
+use winit::{
+    event_loop::EventLoopBuilder,
+    platform::{run_on_demand::EventLoopExtRunOnDemand, windows::EventLoopBuilderExtWindows},
+    tray::TrayBuilder,
+};
+
+#[test]
+fn tray_dropped_after_run_on_demand_is_removed() {
+    let mut event_loop = EventLoopBuilder::new()
+        .with_any_thread(true)
+        .build()
+        .unwrap();
+    let tray = TrayBuilder::new().build(&event_loop).unwrap();
+    let id = tray.id();
+    event_loop.run_on_demand(|_, elwt| elwt.exit()).unwrap();
+
+    // The event loop still exists, but no longer processes the messages of the tray.
+    drop(tray);
+    assert!(id.as_window_id().is_none(), "The tray window outlived its drop");
+}
@@ -0,0 +1,67 @@
+#![cfg(all(target_os = "windows", feature = "tray-soak"))]
+
+use std::time::Duration;
+
+use windows_sys::Win32::System::Threading::{
+    GetCurrentProcess, GetGuiResources, GR_GDIOBJECTS, GR_USEROBJECTS,
+};
+use winit::{
+    event_loop::EventLoopBuilder,
+    platform::{pump_events::EventLoopExtPumpEvents, windows::EventLoopBuilderExtWindows},
+    tray::{TrayBuilder, TrayMenu},
+    window::Icon,
+};
+
+const ITERATIONS: usize = 500;
+/// How many objects may be created by the first trays without being released, e.g. caches of
+/// the system.
+const SLACK: u32 = 32;
+
+fn gui_resources() -> (u32, u32) {
+    unsafe {
+        let process = GetCurrentProcess();
+        (
+            GetGuiResources(process, GR_GDIOBJECTS),
+            GetGuiResources(process, GR_USEROBJECTS),
+        )
+    }
+}
+
+#[test]
+fn tray_churn_releases_resources() {
+    let mut event_loop = EventLoopBuilder::new()
+        .with_any_thread(true)
+        .build()
+        .unwrap();
+    let rgba = [0x80; 16 * 16 * 4].to_vec();
+
+    // Warms up, so that lazily created resources aren't counted as leaks.
+    for _ in 0..2 {
+        drop(TrayBuilder::new().build(&event_loop).unwrap());
+        event_loop.pump_events(Some(Duration::ZERO), |_, _| {});
+    }
+    let (gdi_before, user_before) = gui_resources();
+
+    for i in 0..ITERATIONS {
+        let mut menu = TrayMenu::new();
+        menu.item("Item");
+        let tray = TrayBuilder::new()
+            .with_icon(Icon::from_rgba(rgba.clone(), 16, 16).unwrap())
+            .with_tooltip(format!("Tray {i}"))
+            .with_menu(menu)
+            .build(&event_loop)
+            .unwrap_or_else(|err| panic!("Failed to build tray {i}: {err}"));
+        drop(tray);
+        event_loop.pump_events(Some(Duration::ZERO), |_, _| {});
+    }
+
+    let (gdi_after, user_after) = gui_resources();
+    assert!(
+        gdi_after <= gdi_before + SLACK,
+        "GDI objects grew from {gdi_before} to {gdi_after}"
+    );
+    assert!(
+        user_after <= user_before + SLACK,
+        "USER objects grew from {user_before} to {user_after}"
+    );
+}